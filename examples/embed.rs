@@ -0,0 +1,77 @@
+//! Embeds the interpreter: Rust functions called from Risp, variables set and read
+//! by the host, and errors going both ways. Run with `cargo run --example embed`
+
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use language::{Arity, Error, ErrorKind, Interpreter, Member};
+
+fn main() -> Result<(), Error> {
+    let mut risp = Interpreter::new();
+
+    risp.define("add", "(add a b) sum of two numbers", |a: i32, b: i32| {
+        Ok::<_, Error>(a + b)
+    });
+    risp.define("shout", "(shout s) s in capitals", |s: String| {
+        Ok::<_, Error>(s.to_uppercase())
+    });
+    risp.define(
+        "checked-div",
+        "(checked-div a b) a / b",
+        |a: i32, b: i32| match a.checked_div(b) {
+            Some(q) => Ok(q),
+            None => Err(Error::new(
+                ErrorKind::Thrown("div-by-zero".into()),
+                "division by zero",
+            )),
+        },
+    );
+
+    // closures can hold state of the host
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let sink = log.clone();
+    risp.define_variadic(
+        "log",
+        Arity::AtLeast(0),
+        "(log values...) records values",
+        move |args| {
+            sink.borrow_mut()
+                .extend(args.iter().map(ToString::to_string));
+            Ok(Member::Unit)
+        },
+    );
+
+    risp.set(
+        "limits",
+        HashMap::from([("low".to_string(), 1), ("high".to_string(), 10)]),
+    );
+    risp.set("names", vec!["ada", "grace"]);
+
+    let value = risp.eval_str(
+        r#"
+        (log "start" 1)
+        (let total (add 2 (add 3 4)))
+        (let loud (to-list (map (lambda (n) (shout n)) names)))
+        (try (checked-div 1 0) (catch :div-by-zero e (error-message e)))
+        "#,
+    )?;
+    println!("last value: {value}");
+    println!("total: {}", risp.get::<i32>("total")?);
+    println!("loud: {:?}", risp.get::<Vec<String>>("loud")?);
+    println!(
+        "limits: {:?}",
+        risp.get::<HashMap<String, i32>>("limits")?.get("high")
+    );
+    println!("logged: {:?}", log.borrow());
+
+    // errors of the program come back typed
+    let error = risp.eval_str("(add 1 \"two\")").unwrap_err();
+    println!("{:?}: {error}", error.kind());
+    let error = risp.eval_str("(throw :oops \"gave up\")").unwrap_err();
+    println!("{:?}: {error}", error.kind());
+    let error = risp.eval_str("(exit 3)").unwrap_err();
+    println!("{:?}: {error}", error.kind());
+
+    // the interpreter is still usable after an error
+    println!("{}", risp.eval_str("(add 40 2)")?);
+    Ok(())
+}
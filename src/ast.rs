@@ -1,8 +1,20 @@
-use std::collections::VecDeque;
+use std::{
+    collections::VecDeque,
+    hash::{Hash, Hasher},
+    mem,
+    rc::Rc,
+};
 
 use crate::{
-    interp::{interpret_sexpr, ProgContext},
+    error::{ErrorValue, RispError},
+    interp::{Builtin, Continuation, Fun},
     lexer::{Symbol, Token, TokenType},
+    list::List,
+    map::{Map, Set},
+    printer::{self, Mode},
+    record::Struct,
+    seq::Seq,
+    vector::Vector,
 };
 
 pub type Ident<'a> = &'a str;
@@ -13,50 +25,90 @@ pub enum Member<'a> {
     Ident(Ident<'a>),
     SExpr(SExpr<'a>),
     String(String),
+    /// `#\a`, `#\space`
+    Char(char),
     NumI(i32),
+    Bool(bool),
+    /// `:name`, evaluates to itself
+    Keyword(Ident<'a>),
+    List(List<'a>),
+    /// Lazy sequence, see [`Seq`]
+    Seq(Rc<Seq<'a>>),
+    /// `[1 2 3]`
+    Vector(Vector<'a>),
+    Map(Map<'a>),
+    Set(Set<'a>),
+    /// Value of a type defined with `defstruct`
+    Struct(Rc<Struct<'a>>),
+    Error(Rc<ErrorValue<'a>>),
+    Fun(Rc<Fun<'a>>),
+    /// Function or special form implemented in Rust, see [`Builtin`]
+    Builtin(Rc<Builtin<'a>>),
+    Continuation(Rc<Continuation<'a>>),
     Unit,
 }
 
+#[allow(clippy::wrong_self_convention)]
 impl<'a> Member<'a> {
-    pub fn resolve(self, context: &mut ProgContext<'a>) -> Member<'a> {
-        let mut value = self;
-        loop {
-            match value {
-                Member::Ident(i) => {
-                    value = context.get_var(i);
-                }
-                Member::SExpr(s) => {
-                    value = interpret_sexpr(s, context).unwrap();
-                }
-                _ => return value,
-            }
+    pub fn into_string_value(self) -> Result<String, RispError> {
+        match self {
+            Self::Ident(s) => Err(RispError::Type(format!(
+                "Cannot convert the unresolved name {s} into a string"
+            ))),
+            Self::SExpr(s) => Err(RispError::Type(format!(
+                "Cannot convert the unevaluated expression {s} into a string"
+            ))),
+            value => Ok(printer::print(&value, Mode::Display)),
         }
     }
 
-    pub fn into_string_value(self, context: &mut ProgContext<'a>) -> String {
+    pub fn into_inum_value(self) -> Result<i32, RispError> {
         match self {
-            Self::Ident(s) => panic!("Ident {s} not resolved (into_string)"),
-            Self::SExpr(s) => interpret_sexpr(s, context)
-                .unwrap()
-                .into_string_value(context),
-            Self::String(s) => s.to_string(),
-            Self::NumI(n) => n.to_string(),
-            Self::Unit => "()".to_string(),
+            Self::Ident(s) => Err(RispError::Type(format!(
+                "Cannot convert the unresolved name {s} into i32"
+            ))),
+            Self::SExpr(s) => Err(RispError::Type(format!(
+                "Cannot convert the unevaluated expression {s} into i32"
+            ))),
+            Self::String(s) => s
+                .parse::<i32>()
+                .map_err(|_| RispError::Type(format!("Cannot convert string {s} into i32"))),
+            Self::Char(_) => Err(RispError::Type("Cannot convert a char into i32".into())),
+            Self::NumI(n) => Ok(n),
+            Self::Bool(b) => Ok(b as i32),
+            Self::Keyword(k) => Err(RispError::Type(format!(
+                "Cannot convert keyword :{k} into i32"
+            ))),
+            Self::List(_) => Err(RispError::Type("Cannot convert a list into i32".into())),
+            Self::Seq(_) => Err(RispError::Type("Cannot convert a sequence into i32".into())),
+            Self::Vector(_) => Err(RispError::Type("Cannot convert a vector into i32".into())),
+            Self::Map(_) => Err(RispError::Type("Cannot convert a map into i32".into())),
+            Self::Set(_) => Err(RispError::Type("Cannot convert a set into i32".into())),
+            Self::Struct(s) => Err(RispError::Type(format!(
+                "Cannot convert a {} into i32",
+                s.ty.name
+            ))),
+            Self::Error(_) => Err(RispError::Type("Cannot convert an error into i32".into())),
+            Self::Fun(_) | Self::Builtin(_) => {
+                Err(RispError::Type("Cannot convert a function into i32".into()))
+            }
+            Self::Continuation(_) => Err(RispError::Type(
+                "Cannot convert a continuation into i32".into(),
+            )),
+            Self::Unit => Ok(1),
         }
     }
 
-    pub fn into_inum_value(self, context: &mut ProgContext<'a>) -> i32 {
+    /// Only `false` and `()` are false, everything else counts as true
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Self::Bool(false) | Self::Unit)
+    }
+
+    /// Sum type of a value built by the constructor of a `deftype` variant
+    pub fn variant_of(&self) -> Option<Ident<'a>> {
         match self {
-            Self::Ident(s) => panic!("Ident {s} not resolved (into_string)"),
-            Self::SExpr(s) => interpret_sexpr(s, context)
-                .unwrap()
-                .into_inum_value(context),
-            Self::String(s) => match s.parse::<i32>() {
-                Ok(n) => n,
-                Err(_) => panic!("Cannot convert string {s} into i32"),
-            },
-            Self::NumI(n) => n,
-            Self::Unit => 1,
+            Self::Struct(s) => s.ty.sum,
+            _ => None,
         }
     }
 
@@ -67,38 +119,156 @@ impl<'a> Member<'a> {
         }
     }
 
-    pub fn as_ident_list(self) -> Option<Vec<Ident<'a>>> {
+    pub fn as_sexpr(self) -> Option<SExpr<'a>> {
         match self {
-            Self::SExpr(s) => {
-                if s.members.iter().all(|m| match m {
-                    Self::Ident(_) => true,
-                    _ => false,
-                }) {
-                    return Some(
-                        s.members
-                            .into_iter()
-                            .map(|m| match m {
-                                Self::Ident(i) => i,
-                                _ => unreachable!(),
-                            })
-                            .collect(),
-                    );
+            Self::SExpr(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// Drops the values without recursing on the stack for collections nested in them,
+/// however deep. Each collection hands over the values only it holds, which are then
+/// taken apart here in turn
+pub fn drop_values(mut values: Vec<Member<'_>>) {
+    while let Some(value) = values.pop() {
+        match value {
+            Member::List(mut list) => list.take_values(&mut values),
+            Member::Vector(mut vector) => vector.take_values(&mut values),
+            Member::Map(mut map) => map.take_values(&mut values),
+            Member::Set(mut set) => set.take_values(&mut values),
+            Member::SExpr(mut s) => values.extend(s.members.drain(..)),
+            Member::Struct(s) => {
+                if let Ok(mut s) = Rc::try_unwrap(s) {
+                    s.take_values(&mut values)
                 }
             }
             _ => (),
         }
-        None
     }
+}
 
-    pub fn as_sexpr(self) -> Option<SExpr<'a>> {
-        match self {
-            Self::SExpr(s) => Some(s),
-            _ => None,
+impl PartialEq for Member<'_> {
+    // the values nested in collections are compared from a worklist rather than by
+    // recursing, as in `drop_values`
+    fn eq(&self, other: &Self) -> bool {
+        let mut pairs = vec![(self, other)];
+        while let Some(pair) = pairs.pop() {
+            let same = match pair {
+                (Self::Ident(a), Self::Ident(b)) => a == b,
+                (Self::SExpr(a), Self::SExpr(b)) => a == b,
+                (Self::String(a), Self::String(b)) => a == b,
+                (Self::Char(a), Self::Char(b)) => a == b,
+                (Self::NumI(a), Self::NumI(b)) => a == b,
+                (Self::Bool(a), Self::Bool(b)) => a == b,
+                (Self::Keyword(a), Self::Keyword(b)) => a == b,
+                (Self::List(a), Self::List(b)) => {
+                    pairs.extend(a.iter().zip(b.iter()));
+                    a.len() == b.len()
+                }
+                (Self::Vector(a), Self::Vector(b)) => {
+                    pairs.extend(a.iter().zip(b.iter()));
+                    a.len() == b.len()
+                }
+                (Self::Map(a), Self::Map(b)) => a.pair_entries(b, &mut pairs),
+                (Self::Set(a), Self::Set(b)) => a.pair_values(b, &mut pairs),
+                // structs of types defined apart are different, even with the same name and fields
+                (Self::Struct(a), Self::Struct(b)) => {
+                    pairs.extend(a.values.iter().zip(b.values.iter()));
+                    Rc::ptr_eq(&a.ty, &b.ty)
+                }
+                (Self::Seq(a), Self::Seq(b)) => a == b,
+                (Self::Error(a), Self::Error(b)) => a == b,
+                (Self::Fun(a), Self::Fun(b)) => a == b,
+                (Self::Builtin(a), Self::Builtin(b)) => a == b,
+                (Self::Continuation(a), Self::Continuation(b)) => a == b,
+                (Self::Unit, Self::Unit) => true,
+                _ => false,
+            };
+            if !same {
+                return false;
+            }
         }
+        true
     }
 }
 
-#[derive(Debug, Clone)]
+// every value is equal to itself, there are no floats
+impl Eq for Member<'_> {}
+
+impl Hash for Member<'_> {
+    // hashes the values nested in lists, vectors and structs from a worklist, see `eq`
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_nested_maps(self);
+        let mut values = vec![self];
+        while let Some(value) = values.pop() {
+            mem::discriminant(value).hash(state);
+            match value {
+                Self::Ident(i) => i.hash(state),
+                Self::SExpr(s) => s.hash(state),
+                Self::String(s) => s.hash(state),
+                Self::Char(c) => c.hash(state),
+                Self::NumI(n) => n.hash(state),
+                Self::Bool(b) => b.hash(state),
+                Self::Keyword(k) => k.hash(state),
+                Self::List(l) => {
+                    l.len().hash(state);
+                    values.extend(l.iter());
+                }
+                Self::Vector(v) => {
+                    v.len().hash(state);
+                    values.extend(v.iter());
+                }
+                Self::Struct(s) => {
+                    s.ty.name.hash(state);
+                    values.extend(&s.values);
+                }
+                Self::Map(m) => m.hash_code().hash(state),
+                Self::Set(s) => s.hash_code().hash(state),
+                // compared by identity
+                Self::Seq(s) => Rc::as_ptr(s).hash(state),
+                Self::Continuation(c) => Rc::as_ptr(c).hash(state),
+                // equal errors and functions share at least these
+                Self::Error(e) => (e.kind, e.message()).hash(state),
+                Self::Fun(f) => f.name().hash(state),
+                Self::Builtin(b) => b.name.hash(state),
+                Self::Unit => (),
+            }
+        }
+    }
+}
+
+/// Maps and sets keep the hash of their entries once worked out. Those nested in `value`
+/// are hashed here from the innermost out, so that hashing one never recurses into another
+fn hash_nested_maps(value: &Member) {
+    let mut maps = Vec::new();
+    let mut values = vec![value];
+    while let Some(value) = values.pop() {
+        match value {
+            Member::List(l) => values.extend(l.iter()),
+            Member::Vector(v) => values.extend(v.iter()),
+            Member::Struct(s) => values.extend(&s.values),
+            Member::Map(m) if !m.is_hashed() => {
+                maps.push(value);
+                values.extend(m.iter().flat_map(|(key, value)| [key, value]));
+            }
+            Member::Set(s) if !s.is_hashed() => {
+                maps.push(value);
+                values.extend(s.iter());
+            }
+            _ => (),
+        }
+    }
+    for map in maps.into_iter().rev() {
+        match map {
+            Member::Map(m) => m.hash_code(),
+            Member::Set(s) => s.hash_code(),
+            _ => continue,
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct SExpr<'a> {
     pub members: VecDeque<Member<'a>>,
 }
@@ -109,8 +279,20 @@ impl<'a> SExpr<'a> {
             members: members.into(),
         }
     }
+
+    pub fn into_members(mut self) -> VecDeque<Member<'a>> {
+        mem::take(&mut self.members)
+    }
+}
+
+impl Drop for SExpr<'_> {
+    // expressions nested in expressions would otherwise be dropped recursively
+    fn drop(&mut self) {
+        drop_values(self.members.drain(..).collect());
+    }
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug)]
 pub struct AST<'a> {
     pub prog: Vec<SExpr<'a>>,
@@ -121,11 +303,11 @@ impl<'a> TryInto<AST<'a>> for Vec<Token<'a>> {
     fn try_into(mut self) -> Result<AST<'a>, Self::Error> {
         let mut prog = Vec::new();
         self.reverse();
-        while self.len() > 0 {
+        while !self.is_empty() {
             let first = self.pop().ok_or("Empty input list")?;
             match first.ty {
                 TokenType::Symbol(Symbol::LParen) => {
-                    prog.push(take_until_match_parens(&mut self)?);
+                    prog.push(take_until_match_parens(&mut self, Symbol::RParen)?);
                 }
                 _ => return Err("Error parsing program into AST"),
             }
@@ -140,35 +322,163 @@ fn read_string<'a>(tokens: &mut Vec<Token<'a>>) -> Result<String, &'static str>
         match tokens.pop() {
             Some(tok) => match tok.ty {
                 TokenType::Symbol(Symbol::Quote) => return Ok(st),
-                _ => st.push_str(tok.reclaim()),
+                _ => st.push_str(&unescape(tok.reclaim())?),
             },
             None => return Err("Unterminated string literal"),
         }
     }
 }
 
-fn take_until_match_parens<'a>(tokens: &mut Vec<Token<'a>>) -> Result<SExpr<'a>, &'static str> {
-    let mut base = SExpr::new(vec![]);
+/// Replaces the escapes `\"`, `\\`, `\n`, `\t`, `\r` and `\0` of a string literal
+fn unescape(raw: &str) -> Result<String, &'static str> {
+    let mut st = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        st.push(match c {
+            '\\' => match chars.next() {
+                Some('"') => '"',
+                Some('\\') => '\\',
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('r') => '\r',
+                Some('0') => '\0',
+                _ => return Err("Unknown escape in string literal"),
+            },
+            c => c,
+        });
+    }
+    Ok(st)
+}
+
+/// String as it's written in a literal, the inverse of [`unescape`]
+pub fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\0' => escaped.push_str("\\0"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// `#\a`, or a named char like `#\space`
+fn read_char(literal: &str) -> Result<char, &'static str> {
+    let mut chars = literal.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(c);
+    }
+    Ok(match literal {
+        "space" => ' ',
+        "newline" => '\n',
+        "tab" => '\t',
+        "return" => '\r',
+        "nul" => '\0',
+        _ => return Err("Unknown character name"),
+    })
+}
+
+/// How deep expressions can be nested in the source. Cloning and comparing them
+/// recurses once per level
+pub const MAX_NESTING: usize = 1000;
+
+/// Expression being read, up to its closing delimiter
+struct Open<'a> {
+    expr: SExpr<'a>,
+    close: Symbol,
+}
+
+impl<'a> Open<'a> {
+    /// `[...]` reads as a call to `vector`, `{k v ...}` and `#{v ...}` to `hash-map`
+    /// and `hash-set`
+    fn new(close: Symbol, constructor: Option<Ident<'a>>) -> Self {
+        Self {
+            expr: SExpr::new(constructor.map(Member::Ident).into_iter().collect()),
+            close,
+        }
+    }
+}
+
+/// Reads members up to `close`, and the expressions nested in them from a stack
+fn take_until_match_parens<'a>(
+    tokens: &mut Vec<Token<'a>>,
+    close: Symbol,
+) -> Result<SExpr<'a>, &'static str> {
+    // the innermost expression last
+    let mut open = vec![Open::new(close, None)];
     loop {
-        match tokens.pop() {
-            Some(token) => match token.ty {
-                TokenType::Symbol(sym) => match sym {
-                    Symbol::RParen => return Ok(base),
-                    Symbol::LParen => base
-                        .members
-                        .push_back(Member::SExpr(take_until_match_parens(tokens)?)),
-                    Symbol::Quote => base.members.push_back(Member::String(read_string(tokens)?)),
-                },
-                _ => {
-                    let reclaimed = token.reclaim();
-                    if let Ok(num) = reclaimed.parse::<i32>() {
-                        base.members.push_back(Member::NumI(num));
-                    } else {
-                        base.members.push_back(Member::Ident(reclaimed));
+        let Some(token) = tokens.pop() else {
+            // unterminated expressions end with the input
+            let mut expr = open.pop().unwrap().expr;
+            while let Some(mut outer) = open.pop() {
+                outer.expr.members.push_back(Member::SExpr(expr));
+                expr = outer.expr;
+            }
+            return Ok(expr);
+        };
+        let member = match token.ty {
+            TokenType::Symbol(sym) => match sym {
+                Symbol::RParen | Symbol::RBracket | Symbol::RBrace
+                    if sym == open.last().unwrap().close =>
+                {
+                    let expr = open.pop().unwrap().expr;
+                    if sym == Symbol::RBrace
+                        && expr.members.front() == Some(&Member::Ident("hash-map"))
+                        && expr.members.len().is_multiple_of(2)
+                    {
+                        return Err("Map literals need a value for every key");
+                    }
+                    match open.is_empty() {
+                        true => return Ok(expr),
+                        false => Member::SExpr(expr),
                     }
                 }
+                Symbol::RParen | Symbol::RBracket | Symbol::RBrace => {
+                    return Err("Mismatched closing delimiter")
+                }
+                Symbol::LParen | Symbol::LBracket | Symbol::LBrace | Symbol::HashBrace
+                    if open.len() == MAX_NESTING =>
+                {
+                    return Err("Expressions nested too deeply")
+                }
+                Symbol::LParen | Symbol::LBracket | Symbol::LBrace | Symbol::HashBrace => {
+                    open.push(match sym {
+                        Symbol::LBracket => Open::new(Symbol::RBracket, Some("vector")),
+                        Symbol::LBrace => Open::new(Symbol::RBrace, Some("hash-map")),
+                        Symbol::HashBrace => Open::new(Symbol::RBrace, Some("hash-set")),
+                        _ => Open::new(Symbol::RParen, None),
+                    });
+                    continue;
+                }
+                Symbol::Quote => Member::String(read_string(tokens)?),
+                // `#"Hello {name}"` reads as `(format "Hello {name}")`, whose names
+                // are looked up as variables where it's evaluated
+                Symbol::HashQuote => Member::SExpr(SExpr::new(vec![
+                    Member::Ident("format"),
+                    Member::String(read_string(tokens)?),
+                ])),
             },
-            None => return Ok(base),
-        }
+            _ => {
+                let reclaimed = token.reclaim();
+                if let Some(literal) = reclaimed.strip_prefix("#\\") {
+                    Member::Char(read_char(literal)?)
+                } else if let Ok(num) = reclaimed.parse::<i32>() {
+                    Member::NumI(num)
+                } else if let Ok(b) = reclaimed.parse::<bool>() {
+                    Member::Bool(b)
+                } else if let Some(keyword) = reclaimed.strip_prefix(':').filter(|k| !k.is_empty())
+                {
+                    Member::Keyword(keyword)
+                } else {
+                    Member::Ident(reclaimed)
+                }
+            }
+        };
+        open.last_mut().unwrap().expr.members.push_back(member);
     }
 }
@@ -0,0 +1,139 @@
+use std::io;
+
+use crate::{
+    ast::Member,
+    error::RispError,
+    format::displayed,
+    interp::{Arity, Builtin, ProgContext},
+    printer,
+};
+
+type OpResult<'a> = Result<Member<'a>, RispError>;
+
+/// `(print value ...)` displays the values separated by spaces, `println` ends the line
+fn fun_std_print<'a>(
+    args: Vec<Member<'a>>,
+    end: &str,
+    context: &mut ProgContext<'a>,
+) -> OpResult<'a> {
+    let text = args
+        .into_iter()
+        .map(displayed)
+        .collect::<Result<Vec<_>, _>>()?
+        .join(" ");
+    emit(&(text + end), context)
+}
+
+fn fun_std_write_one<'a>(
+    name: &str,
+    args: Vec<Member<'a>>,
+    render: fn(Member<'a>) -> Result<String, RispError>,
+    context: &mut ProgContext<'a>,
+) -> OpResult<'a> {
+    let [value] = <[Member<'a>; 1]>::try_from(args)
+        .map_err(|_| RispError::Arity(format!("{name} expects 1 argument")))?;
+    emit(&render(value)?, context)
+}
+
+/// `(read-line)` is the next line without its line break, `()` at the end of the input
+fn fun_std_read_line<'a>(context: &mut ProgContext<'a>) -> OpResult<'a> {
+    flush(context)?;
+    let mut line = String::new();
+    match context.input().read_line(&mut line).map_err(input_error)? {
+        0 => Ok(Member::Unit),
+        _ => {
+            let trimmed = line.strip_suffix('\n').unwrap_or(&line);
+            Ok(Member::String(
+                trimmed.strip_suffix('\r').unwrap_or(trimmed).to_string(),
+            ))
+        }
+    }
+}
+
+fn fun_std_read_all<'a>(context: &mut ProgContext<'a>) -> OpResult<'a> {
+    flush(context)?;
+    let mut text = String::new();
+    context
+        .input()
+        .read_to_string(&mut text)
+        .map_err(input_error)?;
+    Ok(Member::String(text))
+}
+
+fn emit<'a>(text: &str, context: &mut ProgContext<'a>) -> OpResult<'a> {
+    context
+        .output()
+        .write_all(text.as_bytes())
+        .map_err(|e| RispError::Runtime(format!("Could not write output: {e}")))?;
+    flush(context)?;
+    Ok(Member::Unit)
+}
+
+fn flush(context: &mut ProgContext) -> Result<(), RispError> {
+    context
+        .output()
+        .flush()
+        .map_err(|e| RispError::Runtime(format!("Could not write output: {e}")))
+}
+
+fn input_error(e: io::Error) -> RispError {
+    RispError::Runtime(format!("Could not read input: {e}"))
+}
+
+/// Console builtins, writing to and reading from the streams of the context.
+/// Output is flushed after every call so it shows up before the program waits for input
+pub fn builtins<'a>() -> Vec<Builtin<'a>> {
+    use Arity::{AtLeast, Between, Exactly};
+    vec![
+        Builtin::value(
+            "print",
+            AtLeast(0),
+            "(print values...) writes the values displayed, separated by spaces",
+            |_, args, context| fun_std_print(args, "", context),
+        ),
+        Builtin::value(
+            "println",
+            AtLeast(0),
+            "(println values...) print followed by a newline",
+            |_, args, context| fun_std_print(args, "\n", context),
+        ),
+        Builtin::value(
+            "display",
+            Exactly(1),
+            "(display value) writes the value as displayed to a person",
+            |name, args, context| fun_std_write_one(name, args, displayed, context),
+        ),
+        Builtin::value(
+            "write",
+            Exactly(1),
+            "(write value) writes the value as written in code",
+            |name, args, context| {
+                fun_std_write_one(name, args, |value| Ok(value.to_string()), context)
+            },
+        ),
+        Builtin::value(
+            "pprint",
+            Between(1, 2),
+            "(pprint value [width]) writes the value pretty printed",
+            |_, args, context| emit(&(printer::pretty_args(args)? + "\n"), context),
+        ),
+        Builtin::value(
+            "newline",
+            Exactly(0),
+            "(newline) writes a newline",
+            |_, _, context| emit("\n", context),
+        ),
+        Builtin::value(
+            "read-line",
+            Exactly(0),
+            "(read-line) next line of input, () at the end of it",
+            |_, _, context| fun_std_read_line(context),
+        ),
+        Builtin::value(
+            "read-all",
+            Exactly(0),
+            "(read-all) rest of the input",
+            |_, _, context| fun_std_read_all(context),
+        ),
+    ]
+}
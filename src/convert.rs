@@ -0,0 +1,182 @@
+use std::{collections::HashMap, hash::Hash};
+
+use crate::{
+    ast::Member,
+    error::{Error, ErrorKind},
+    list::List,
+    map::Map,
+};
+
+/// Rust value a host can hand a program, as a Risp value
+pub trait IntoRisp<'a> {
+    fn into_risp(self) -> Member<'a>;
+}
+
+/// Rust value a host can take from a Risp value, failing with an
+/// [`ErrorKind::Type`] error when the value doesn't fit
+pub trait FromRisp<'a>: Sized {
+    fn from_risp(value: Member<'a>) -> Result<Self, Error>;
+}
+
+fn expected(what: &str, value: &Member) -> Error {
+    Error::new(ErrorKind::Type, format!("Expected {what}, got {value}"))
+}
+
+impl<'a> IntoRisp<'a> for Member<'a> {
+    fn into_risp(self) -> Member<'a> {
+        self
+    }
+}
+
+impl<'a> FromRisp<'a> for Member<'a> {
+    fn from_risp(value: Member<'a>) -> Result<Self, Error> {
+        Ok(value)
+    }
+}
+
+impl<'a> IntoRisp<'a> for () {
+    fn into_risp(self) -> Member<'a> {
+        Member::Unit
+    }
+}
+
+impl<'a> FromRisp<'a> for () {
+    fn from_risp(value: Member<'a>) -> Result<Self, Error> {
+        match value {
+            Member::Unit => Ok(()),
+            other => Err(expected("()", &other)),
+        }
+    }
+}
+
+impl<'a> IntoRisp<'a> for i32 {
+    fn into_risp(self) -> Member<'a> {
+        Member::NumI(self)
+    }
+}
+
+impl<'a> FromRisp<'a> for i32 {
+    fn from_risp(value: Member<'a>) -> Result<Self, Error> {
+        match value {
+            Member::NumI(n) => Ok(n),
+            other => Err(expected("a number", &other)),
+        }
+    }
+}
+
+impl<'a> IntoRisp<'a> for bool {
+    fn into_risp(self) -> Member<'a> {
+        Member::Bool(self)
+    }
+}
+
+impl<'a> FromRisp<'a> for bool {
+    fn from_risp(value: Member<'a>) -> Result<Self, Error> {
+        match value {
+            Member::Bool(b) => Ok(b),
+            other => Err(expected("a boolean", &other)),
+        }
+    }
+}
+
+impl<'a> IntoRisp<'a> for char {
+    fn into_risp(self) -> Member<'a> {
+        Member::Char(self)
+    }
+}
+
+impl<'a> FromRisp<'a> for char {
+    fn from_risp(value: Member<'a>) -> Result<Self, Error> {
+        match value {
+            Member::Char(c) => Ok(c),
+            other => Err(expected("a character", &other)),
+        }
+    }
+}
+
+impl<'a> IntoRisp<'a> for String {
+    fn into_risp(self) -> Member<'a> {
+        Member::String(self)
+    }
+}
+
+impl<'a> IntoRisp<'a> for &str {
+    fn into_risp(self) -> Member<'a> {
+        Member::String(self.to_string())
+    }
+}
+
+impl<'a> FromRisp<'a> for String {
+    fn from_risp(value: Member<'a>) -> Result<Self, Error> {
+        match value {
+            Member::String(s) => Ok(s),
+            other => Err(expected("a string", &other)),
+        }
+    }
+}
+
+/// `()` for `None`
+impl<'a, T: IntoRisp<'a>> IntoRisp<'a> for Option<T> {
+    fn into_risp(self) -> Member<'a> {
+        self.map_or(Member::Unit, T::into_risp)
+    }
+}
+
+impl<'a, T: FromRisp<'a>> FromRisp<'a> for Option<T> {
+    fn from_risp(value: Member<'a>) -> Result<Self, Error> {
+        match value {
+            Member::Unit => Ok(None),
+            value => T::from_risp(value).map(Some),
+        }
+    }
+}
+
+/// A list
+impl<'a, T: IntoRisp<'a>> IntoRisp<'a> for Vec<T> {
+    fn into_risp(self) -> Member<'a> {
+        Member::List(self.into_iter().map(T::into_risp).collect::<List>())
+    }
+}
+
+/// From a list, a vector, or a sequence realized to its end
+impl<'a, T: FromRisp<'a>> FromRisp<'a> for Vec<T> {
+    fn from_risp(value: Member<'a>) -> Result<Self, Error> {
+        let values: Vec<_> = match &value {
+            Member::List(list) => list.iter().cloned().collect(),
+            Member::Vector(vector) => vector.iter().cloned().collect(),
+            Member::Seq(seq) => match seq.realized() {
+                (values, true) => values,
+                (_, false) => return Err(expected("a realized sequence", &value)),
+            },
+            other => return Err(expected("a list or a vector", other)),
+        };
+        values.into_iter().map(T::from_risp).collect()
+    }
+}
+
+/// A map
+impl<'a, K: IntoRisp<'a>, V: IntoRisp<'a>> IntoRisp<'a> for HashMap<K, V> {
+    fn into_risp(self) -> Member<'a> {
+        Member::Map(
+            self.into_iter()
+                .map(|(key, value)| (key.into_risp(), value.into_risp()))
+                .collect::<Map>(),
+        )
+    }
+}
+
+impl<'a, K, V> FromRisp<'a> for HashMap<K, V>
+where
+    K: FromRisp<'a> + Eq + Hash,
+    V: FromRisp<'a>,
+{
+    fn from_risp(value: Member<'a>) -> Result<Self, Error> {
+        match value {
+            Member::Map(map) => map
+                .iter()
+                .map(|(key, value)| Ok((K::from_risp(key.clone())?, V::from_risp(value.clone())?)))
+                .collect(),
+            other => Err(expected("a map", &other)),
+        }
+    }
+}
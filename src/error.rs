@@ -0,0 +1,231 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+use crate::ast::Ident;
+
+/// How many of the innermost calls a stack overflow reports
+const SHOWN_FRAMES: usize = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RispError {
+    /// Calls nested deeper than the context allows, `frames` holds the innermost calls first
+    StackOverflow {
+        depth: usize,
+        frames: Vec<String>,
+    },
+    /// Variable or function that isn't bound in any scope
+    Unbound(String),
+    /// Arguments that don't fit the parameters of the function called
+    Arity(String),
+    /// Value of the wrong type for an operation
+    Type(String),
+    Runtime(String),
+    /// Operation needing a capability the host didn't grant, like file system access
+    Denied(String),
+    /// File system operation that failed
+    Io(String),
+    /// `exit` called with this code, unwinds the whole program and can't be caught
+    Exit(i32),
+    /// Raised from Risp with `throw` and never caught
+    Thrown {
+        kind: String,
+        message: String,
+    },
+}
+
+impl RispError {
+    /// Name of the keyword `catch` clauses select this error with
+    pub fn kind(&self) -> &str {
+        match self {
+            Self::StackOverflow { .. } => "stack-overflow",
+            Self::Unbound(_) => "unbound",
+            Self::Arity(_) => "arity",
+            Self::Type(_) => "type",
+            Self::Runtime(_) => "error",
+            Self::Denied(_) => "denied",
+            Self::Io(_) => "io",
+            Self::Exit(_) => "exit",
+            Self::Thrown { kind, .. } => kind,
+        }
+    }
+}
+
+impl From<String> for RispError {
+    fn from(msg: String) -> Self {
+        Self::Runtime(msg)
+    }
+}
+
+impl From<&str> for RispError {
+    fn from(msg: &str) -> Self {
+        Self::Runtime(msg.to_string())
+    }
+}
+
+impl Display for RispError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::StackOverflow { depth, frames } => {
+                write!(f, "Stack overflow: calls nested deeper than {depth}")?;
+                for frame in frames.iter().take(SHOWN_FRAMES) {
+                    write!(f, "\n    in {frame}")?;
+                }
+                if frames.len() > SHOWN_FRAMES {
+                    write!(f, "\n    ... {} more", frames.len() - SHOWN_FRAMES)?;
+                }
+                Ok(())
+            }
+            Self::Unbound(msg)
+            | Self::Arity(msg)
+            | Self::Type(msg)
+            | Self::Runtime(msg)
+            | Self::Denied(msg)
+            | Self::Io(msg) => write!(f, "{msg}"),
+            Self::Exit(code) => write!(f, "Exited with code {code}"),
+            Self::Thrown { kind, message } => write!(f, "Uncaught :{kind} {message}"),
+        }
+    }
+}
+
+/// Error as a Risp value, what `catch` clauses bind
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorValue<'a> {
+    pub kind: Ident<'a>,
+    pub error: RispError,
+    /// Functions that were being called when the error was raised, innermost first
+    pub stack: Vec<String>,
+}
+
+impl<'a> ErrorValue<'a> {
+    pub fn new(kind: Ident<'a>, message: String, stack: Vec<String>) -> Self {
+        Self {
+            kind,
+            error: RispError::Thrown {
+                kind: kind.to_string(),
+                message,
+            },
+            stack,
+        }
+    }
+
+    /// Internal error raised while evaluating
+    pub fn from_error(error: RispError, stack: Vec<String>) -> Self {
+        let kind = match &error {
+            RispError::StackOverflow { .. } => "stack-overflow",
+            RispError::Unbound(_) => "unbound",
+            RispError::Arity(_) => "arity",
+            RispError::Type(_) => "type",
+            RispError::Denied(_) => "denied",
+            RispError::Io(_) => "io",
+            RispError::Exit(_) => "exit",
+            RispError::Runtime(_) | RispError::Thrown { .. } => "error",
+        };
+        Self { kind, error, stack }
+    }
+
+    pub fn message(&self) -> String {
+        match &self.error {
+            RispError::Thrown { message, .. } => message.clone(),
+            error => error.to_string(),
+        }
+    }
+}
+
+/// What went wrong, for hosts embedding the interpreter. New kinds may be added
+/// without breaking them
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Source that isn't valid Risp
+    Parse,
+    /// File that couldn't be read, or I/O failing in a builtin
+    Io,
+    /// Variable or function that isn't bound
+    Unbound,
+    /// Wrong number of arguments
+    Arity,
+    /// Value of the wrong type, including one a host function can't convert
+    Type,
+    /// Capability the host didn't grant
+    Denied,
+    StackOverflow,
+    /// Raised from Risp with `throw` and never caught, with the name of its keyword
+    Thrown(String),
+    /// The program called `exit` with this code
+    Exit(i32),
+    /// Any other error of the evaluator or of a host function
+    Runtime,
+}
+
+/// Error of the embedding API, see [`crate::Interpreter`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    kind: ErrorKind,
+    message: String,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl From<RispError> for Error {
+    fn from(error: RispError) -> Self {
+        let kind = match &error {
+            RispError::StackOverflow { .. } => ErrorKind::StackOverflow,
+            RispError::Unbound(_) => ErrorKind::Unbound,
+            RispError::Arity(_) => ErrorKind::Arity,
+            RispError::Type(_) => ErrorKind::Type,
+            RispError::Runtime(_) => ErrorKind::Runtime,
+            RispError::Denied(_) => ErrorKind::Denied,
+            RispError::Io(_) => ErrorKind::Io,
+            RispError::Exit(code) => ErrorKind::Exit(*code),
+            RispError::Thrown { kind, message } => {
+                return Self::new(ErrorKind::Thrown(kind.clone()), message.clone())
+            }
+        };
+        Self::new(kind, error.to_string())
+    }
+}
+
+// raised by a host function, the program can catch it like any other
+impl From<Error> for RispError {
+    fn from(error: Error) -> Self {
+        let Error { kind, message } = error;
+        match kind {
+            ErrorKind::Io => Self::Io(message),
+            ErrorKind::Unbound => Self::Unbound(message),
+            ErrorKind::Arity => Self::Arity(message),
+            ErrorKind::Type => Self::Type(message),
+            ErrorKind::Denied => Self::Denied(message),
+            ErrorKind::Thrown(kind) => Self::Thrown { kind, message },
+            ErrorKind::Exit(code) => Self::Exit(code),
+            ErrorKind::Parse | ErrorKind::StackOverflow | ErrorKind::Runtime => {
+                Self::Runtime(message)
+            }
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match &self.kind {
+            ErrorKind::Thrown(kind) => write!(f, "Uncaught :{kind} {}", self.message),
+            _ => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,263 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    ast::Member,
+    error::RispError,
+    format::displayed,
+    interp::{Arity, Builtin, ProgContext},
+};
+
+type OpResult<'a> = Result<Member<'a>, RispError>;
+
+/// Capability to use the file system the host grants a program, without it every file
+/// builtin fails with a `:denied` error
+#[derive(Debug, Clone)]
+pub struct FsAccess {
+    /// Directory the program is confined to, relative paths are resolved from it
+    root: Option<PathBuf>,
+}
+
+impl FsAccess {
+    /// Access to every file the process can reach
+    pub fn unrestricted() -> Self {
+        Self { root: None }
+    }
+
+    /// Access to the files under `root` only, which has to exist
+    pub fn within(root: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            root: Some(root.as_ref().canonicalize()?),
+        })
+    }
+
+    /// Path to operate on for `path`, fails if it's outside of the root.
+    /// Symlinks are followed as far as the path exists, so that they can't lead out of it,
+    /// and dangling ones are refused since what they would create could be anywhere
+    fn resolve(&self, path: &str) -> Result<PathBuf, RispError> {
+        let Some(root) = &self.root else {
+            return Ok(PathBuf::from(path));
+        };
+        let denied = || RispError::Denied(format!("{path} is outside of {}", root.display()));
+        let resolved = normalize(&root.join(path));
+        if !resolved.starts_with(root) {
+            return Err(denied());
+        }
+        if let Some(existing) = resolved.ancestors().find(|p| p.symlink_metadata().is_ok()) {
+            if !existing.exists() {
+                return Err(RispError::Denied(format!(
+                    "{path} goes through a dangling symlink"
+                )));
+            }
+            let real = existing.canonicalize().map_err(|e| io_error(path, e))?;
+            if !real.starts_with(root) {
+                return Err(denied());
+            }
+        }
+        Ok(resolved)
+    }
+}
+
+/// Path with `.` and `..` resolved without looking at the file system
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                // there is nothing above the root
+                Some(Component::RootDir | Component::Prefix(_)) => (),
+                _ => normalized.push(".."),
+            },
+            component => normalized.push(component),
+        }
+    }
+    if normalized.as_os_str().is_empty() {
+        normalized.push(".");
+    }
+    normalized
+}
+
+fn io_error(path: &str, e: io::Error) -> RispError {
+    RispError::Io(format!("{path}: {e}"))
+}
+
+fn expect_path<'a>(name: &str, arg: Option<Member<'a>>) -> Result<String, RispError> {
+    match arg {
+        Some(Member::String(path)) => Ok(path),
+        Some(other) => Err(RispError::Type(format!(
+            "{name} expects a path, got {other}"
+        ))),
+        None => Err(RispError::Arity(format!("{name} expects a path"))),
+    }
+}
+
+fn single_path<'a>(name: &str, args: Vec<Member<'a>>) -> Result<String, RispError> {
+    let mut args = args.into_iter();
+    let path = expect_path(name, args.next())?;
+    if args.next().is_some() {
+        return Err(RispError::Arity(format!("{name} expects a path")));
+    }
+    Ok(path)
+}
+
+/// `path` resolved by the file system access of the context, which fails without one
+fn granted(name: &str, path: &str, context: &ProgContext) -> Result<PathBuf, RispError> {
+    let access = context.filesystem().ok_or_else(|| {
+        RispError::Denied(format!(
+            "{name} needs file system access, which this program wasn't granted"
+        ))
+    })?;
+    access.resolve(path)
+}
+
+fn fun_std_path_join<'a>(name: &str, args: Vec<Member<'a>>) -> OpResult<'a> {
+    let mut args = args.into_iter();
+    let mut joined = PathBuf::from(expect_path(name, args.next())?);
+    for part in args {
+        joined.push(expect_path(name, Some(part))?);
+    }
+    Ok(Member::String(joined.to_string_lossy().into_owned()))
+}
+
+fn fun_std_path_normalize<'a>(name: &str, args: Vec<Member<'a>>) -> OpResult<'a> {
+    let path = single_path(name, args)?;
+    let normalized = normalize(Path::new(&path));
+    Ok(Member::String(normalized.to_string_lossy().into_owned()))
+}
+
+/// Runs `op` on the file at the only argument
+fn fun_std_file_op<'a>(
+    name: &str,
+    args: Vec<Member<'a>>,
+    context: &mut ProgContext<'a>,
+    op: fn(&Path) -> io::Result<Member<'a>>,
+) -> OpResult<'a> {
+    let path = single_path(name, args)?;
+    let resolved = granted(name, &path, context)?;
+    op(&resolved).map_err(|e| io_error(&path, e))
+}
+
+/// `write-file` and `append-file`, the content is written as `display` shows it
+fn fun_std_write_file<'a>(
+    name: &str,
+    args: Vec<Member<'a>>,
+    context: &mut ProgContext<'a>,
+    write: fn(&Path, &str) -> io::Result<()>,
+) -> OpResult<'a> {
+    let mut args = args.into_iter();
+    let path = expect_path(name, args.next())?;
+    let (Some(content), None) = (args.next(), args.next()) else {
+        return Err(RispError::Arity(format!(
+            "{name} expects a path and the content to write"
+        )));
+    };
+    let resolved = granted(name, &path, context)?;
+    write(&resolved, &displayed(content)?).map_err(|e| io_error(&path, e))?;
+    Ok(Member::Unit)
+}
+
+/// File system builtins. Paths are strings, `path-join` and `path-normalize`
+/// work on them alone and need no capability
+pub fn builtins<'a>() -> Vec<Builtin<'a>> {
+    use Arity::{AtLeast, Exactly};
+    vec![
+        Builtin::value(
+            "path-join",
+            AtLeast(1),
+            "(path-join path parts...) path of the parts under path",
+            |name, args, _| fun_std_path_join(name, args),
+        ),
+        Builtin::value(
+            "path-normalize",
+            Exactly(1),
+            "(path-normalize path) path with . and .. resolved",
+            |name, args, _| fun_std_path_normalize(name, args),
+        ),
+        Builtin::value(
+            "read-file",
+            Exactly(1),
+            "(read-file path) content of a file",
+            |name, args, context| {
+                fun_std_file_op(name, args, context, |path| {
+                    fs::read_to_string(path).map(Member::String)
+                })
+            },
+        ),
+        Builtin::value(
+            "write-file",
+            Exactly(2),
+            "(write-file path content) replaces the content of a file",
+            |name, args, context| {
+                fun_std_write_file(name, args, context, |path, content| {
+                    fs::write(path, content)
+                })
+            },
+        ),
+        Builtin::value(
+            "append-file",
+            Exactly(2),
+            "(append-file path content) adds to the end of a file",
+            |name, args, context| {
+                fun_std_write_file(name, args, context, |path, content| {
+                    fs::OpenOptions::new()
+                        .append(true)
+                        .create(true)
+                        .open(path)
+                        .and_then(|mut file| file.write_all(content.as_bytes()))
+                })
+            },
+        ),
+        Builtin::value(
+            "file-exists?",
+            Exactly(1),
+            "(file-exists? path) whether there is a file at the path",
+            |name, args, context| {
+                fun_std_file_op(name, args, context, |path| Ok(Member::Bool(path.exists())))
+            },
+        ),
+        // entry names, sorted so that scripts behave the same everywhere
+        Builtin::value(
+            "list-dir",
+            Exactly(1),
+            "(list-dir path) sorted names of the entries of a directory",
+            |name, args, context| {
+                fun_std_file_op(name, args, context, |path| {
+                    let mut names = fs::read_dir(path)?
+                        .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+                        .collect::<io::Result<Vec<_>>>()?;
+                    names.sort();
+                    Ok(Member::List(
+                        names.into_iter().map(Member::String).collect(),
+                    ))
+                })
+            },
+        ),
+        Builtin::value(
+            "mkdir",
+            Exactly(1),
+            "(mkdir path) creates a directory and its parents",
+            |name, args, context| {
+                fun_std_file_op(name, args, context, |path| {
+                    fs::create_dir_all(path).map(|()| Member::Unit)
+                })
+            },
+        ),
+        Builtin::value(
+            "delete-file",
+            Exactly(1),
+            "(delete-file path) deletes a file",
+            |name, args, context| {
+                fun_std_file_op(name, args, context, |path| {
+                    fs::remove_file(path).map(|()| Member::Unit)
+                })
+            },
+        ),
+    ]
+}
@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+
+use crate::{ast::Member, error::RispError, interp::ProgContext, strings::to_radix};
+
+/// Piece of a format string, `{{` and `}}` stand for literal braces
+enum Piece<'s> {
+    Text(String),
+    Directive(Arg<'s>, Spec),
+}
+
+/// Value a directive formats, `{}` takes the next positional argument
+enum Arg<'s> {
+    Next,
+    /// `{0}`
+    Index(usize),
+    /// `{name}`, given as `:name value` among the arguments or else a variable
+    Name(&'s str),
+}
+
+#[derive(Clone, Copy)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    /// `a`, values as they read to a person, strings without quotes and `()` as nothing
+    Display,
+    /// `s`, values the way they are written in Risp code
+    Write,
+    /// `d`, a number in decimal
+    Decimal,
+    /// `x`, `X`, `o` and `b`
+    Radix { radix: u32, upper: bool },
+}
+
+/// `[[fill]align][0][width][.precision][kind]` after the `:` of a directive
+struct Spec {
+    fill: char,
+    align: Option<Align>,
+    /// Zeros go between the sign and the digits of a number
+    zero: bool,
+    width: usize,
+    /// Least digits of a number, most chars of anything else
+    precision: Option<usize>,
+    kind: Kind,
+}
+
+fn align(c: char) -> Option<Align> {
+    match c {
+        '<' => Some(Align::Left),
+        '^' => Some(Align::Center),
+        '>' => Some(Align::Right),
+        _ => None,
+    }
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Result<Self, RispError> {
+        let error = || RispError::Runtime(format!("Invalid format spec :{spec}"));
+        let chars: Vec<char> = spec.chars().collect();
+        let mut parsed = Self {
+            fill: ' ',
+            align: None,
+            zero: false,
+            width: 0,
+            precision: None,
+            kind: Kind::Display,
+        };
+        let mut i = 0;
+        if let Some(a) = chars.get(1).copied().and_then(align) {
+            parsed.fill = chars[0];
+            parsed.align = Some(a);
+            i = 2;
+        } else if let Some(a) = chars.first().copied().and_then(align) {
+            parsed.align = Some(a);
+            i = 1;
+        }
+        if chars.get(i) == Some(&'0') {
+            parsed.zero = true;
+            i += 1;
+        }
+        let number = |i: &mut usize| {
+            let start = *i;
+            while chars.get(*i).is_some_and(char::is_ascii_digit) {
+                *i += 1;
+            }
+            chars[start..*i]
+                .iter()
+                .collect::<String>()
+                .parse::<usize>()
+                .ok()
+        };
+        parsed.width = number(&mut i).unwrap_or(0);
+        if chars.get(i) == Some(&'.') {
+            i += 1;
+            parsed.precision = Some(number(&mut i).ok_or_else(error)?);
+        }
+        if let Some(kind) = chars.get(i) {
+            parsed.kind = match kind {
+                'a' => Kind::Display,
+                's' => Kind::Write,
+                'd' => Kind::Decimal,
+                'x' => Kind::Radix {
+                    radix: 16,
+                    upper: false,
+                },
+                'X' => Kind::Radix {
+                    radix: 16,
+                    upper: true,
+                },
+                'o' => Kind::Radix {
+                    radix: 8,
+                    upper: false,
+                },
+                'b' => Kind::Radix {
+                    radix: 2,
+                    upper: false,
+                },
+                _ => return Err(error()),
+            };
+            i += 1;
+        }
+        match i == chars.len() {
+            true => Ok(parsed),
+            false => Err(error()),
+        }
+    }
+}
+
+fn parse(template: &str) -> Result<Vec<Piece<'_>>, RispError> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '{' if chars.next_if(|(_, c)| *c == '{').is_some() => text.push('{'),
+            '}' if chars.next_if(|(_, c)| *c == '}').is_some() => text.push('}'),
+            '}' => {
+                return Err("Unmatched } in format string, write }} for a brace"
+                    .to_string()
+                    .into())
+            }
+            '{' => {
+                let end = template[i..]
+                    .find('}')
+                    .map(|end| i + end)
+                    .ok_or("Unterminated directive in format string")?;
+                let directive = &template[i + 1..end];
+                while chars.next_if(|(j, _)| *j <= end).is_some() {}
+                let (arg, spec) = directive.split_once(':').unwrap_or((directive, ""));
+                let arg = match arg {
+                    "" => Arg::Next,
+                    arg => match arg.parse::<usize>() {
+                        Ok(index) => Arg::Index(index),
+                        Err(_) => Arg::Name(arg),
+                    },
+                };
+                pieces.push(Piece::Text(std::mem::take(&mut text)));
+                pieces.push(Piece::Directive(arg, Spec::parse(spec)?));
+            }
+            c => text.push(c),
+        }
+    }
+    pieces.push(Piece::Text(text));
+    Ok(pieces)
+}
+
+/// `(format template value ... :name value ...)` fills the directives of the template,
+/// see [`Spec`]. `{}` takes the next value and `{1}` the value at that position,
+/// `{name}` the value given after `:name` or else the variable `name`
+pub fn format<'a>(
+    args: Vec<Member<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Member<'a>, RispError> {
+    let mut args = args.into_iter();
+    let template = match args.next() {
+        Some(Member::String(template)) => template,
+        Some(other) => {
+            return Err(RispError::Type(format!(
+                "format expects a format string, got {other}"
+            )))
+        }
+        None => return Err(RispError::Arity("format expects a format string".into())),
+    };
+    let pieces = parse(&template)?;
+
+    // keywords naming a directive start a named value, anything else is positional
+    let names: Vec<&str> = pieces
+        .iter()
+        .filter_map(|piece| match piece {
+            Piece::Directive(Arg::Name(name), _) => Some(*name),
+            _ => None,
+        })
+        .collect();
+    let mut positional = Vec::new();
+    let mut named = HashMap::new();
+    while let Some(arg) = args.next() {
+        match arg {
+            Member::Keyword(k) if names.contains(&k) => {
+                let value = args.next().ok_or_else(|| {
+                    RispError::Arity(format!("format is missing a value for :{k}"))
+                })?;
+                named.insert(k, value);
+            }
+            arg => positional.push(arg),
+        }
+    }
+
+    let mut out = String::new();
+    let mut next = 0;
+    let mut used = 0;
+    for piece in pieces {
+        let (arg, spec) = match piece {
+            Piece::Text(text) => {
+                out.push_str(&text);
+                continue;
+            }
+            Piece::Directive(arg, spec) => (arg, spec),
+        };
+        let value = match arg {
+            Arg::Next | Arg::Index(_) => {
+                let index = match arg {
+                    Arg::Index(index) => index,
+                    _ => {
+                        next += 1;
+                        next - 1
+                    }
+                };
+                used = used.max(index + 1);
+                positional.get(index).cloned().ok_or_else(|| {
+                    RispError::Arity(format!(
+                        "format has no value at position {index}, it got {}",
+                        positional.len()
+                    ))
+                })?
+            }
+            Arg::Name(name) => match named.get(name) {
+                Some(value) => value.clone(),
+                None => context
+                    .get_var(name)
+                    .map_err(|_| RispError::Unbound(format!("format has no value for {name}")))?,
+            },
+        };
+        out.push_str(&render(value, &spec)?);
+    }
+    if used < positional.len() {
+        return Err(RispError::Arity(format!(
+            "format got {} values but its directives use {used}",
+            positional.len()
+        )));
+    }
+    Ok(Member::String(out))
+}
+
+/// Value as displayed for `a` directives and by `display`, `()` is nothing
+pub fn displayed(value: Member) -> Result<String, RispError> {
+    match value {
+        Member::Unit => Ok(String::new()),
+        value => value.into_string_value(),
+    }
+}
+
+fn render<'a>(value: Member<'a>, spec: &Spec) -> Result<String, RispError> {
+    let number = matches!(value, Member::NumI(_));
+    let text = match spec.kind {
+        Kind::Display | Kind::Write if !number => {
+            let text = match spec.kind {
+                Kind::Display => displayed(value)?,
+                _ => value.to_string(),
+            };
+            match spec.precision {
+                Some(precision) => text.chars().take(precision).collect(),
+                None => text,
+            }
+        }
+        kind => {
+            let n = match value {
+                Member::NumI(n) => n,
+                other => {
+                    return Err(RispError::Type(format!(
+                        "format expects a number for a numeric directive, got {other}"
+                    )))
+                }
+            };
+            let digits = match kind {
+                Kind::Radix { radix, upper: true } => to_radix(n, radix).to_uppercase(),
+                Kind::Radix {
+                    radix,
+                    upper: false,
+                } => to_radix(n, radix),
+                _ => n.to_string(),
+            };
+            let (sign, digits) = match digits.strip_prefix('-') {
+                Some(digits) => ("-", digits.to_string()),
+                None => ("", digits),
+            };
+            let zeros = spec.precision.unwrap_or(0).saturating_sub(digits.len());
+            format!("{sign}{}{digits}", "0".repeat(zeros))
+        }
+    };
+
+    let len = text.chars().count();
+    if len >= spec.width {
+        return Ok(text);
+    }
+    let padding = spec.width - len;
+    if spec.zero && spec.align.is_none() {
+        let (sign, rest) = match text.strip_prefix('-') {
+            Some(rest) if number => ("-", rest),
+            _ => ("", text.as_str()),
+        };
+        return Ok(format!("{sign}{}{rest}", "0".repeat(padding)));
+    }
+    let fill = |n: usize| spec.fill.to_string().repeat(n);
+    let align = spec
+        .align
+        .unwrap_or(if number { Align::Right } else { Align::Left });
+    Ok(match align {
+        Align::Left => text + &fill(padding),
+        Align::Right => fill(padding) + &text,
+        Align::Center => fill(padding / 2) + &text + &fill(padding - padding / 2),
+    })
+}
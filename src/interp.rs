@@ -1,75 +1,558 @@
 use std::{
-    collections::{HashMap, VecDeque},
-    process::exit,
+    cell::RefCell,
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+    io::{self, BufRead, Write},
+    mem,
+    rc::Rc,
+    sync::{LazyLock, Mutex, PoisonError},
 };
 
-use crate::ast::{Ident, Member, SExpr, AST};
+use crate::{
+    ast::{Ident, Member, SExpr, AST},
+    console,
+    error::{ErrorValue, RispError},
+    files::{self, FsAccess},
+    format,
+    list::List,
+    map::{Map, Set},
+    pattern::{Bound, ListPattern, Pattern, Pending},
+    prelude, printer, process,
+    record::{Struct, StructType},
+    seq::{self, Next, Seq, Step},
+    strings,
+    trace::{Event, Level, Tracer},
+    vector::Vector,
+};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Fun<'a> {
-    args: Vec<Ident<'a>>,
-    expr: SExpr<'a>,
+    /// Name it was defined with, `lambda` for anonymous functions
+    name: Ident<'a>,
+    params: ListPattern<'a>,
+    expr: Rc<Member<'a>>,
+    /// What was bound where the function was made, the body sees it wherever it's
+    /// called from. Empty for functions defined at the top level
+    captured: Rc<Env<'a>>,
+    /// Defined with `def` inside a call or block. Its calls bind its name in their
+    /// scope, since it can't be found in the root to recurse
+    local: bool,
+}
+
+/// Variables and functions bound in the scopes a closure was made in,
+/// see [`ProgContext::local_env`]
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Env<'a> {
+    variables: Bindings<'a, Member<'a>>,
+    functions: Bindings<'a, Member<'a>>,
 }
 
 impl<'a> Fun<'a> {
-    fn reg_args(&self, args: Vec<Member<'a>>, context: &mut ProgContext<'a>) -> Result<(), String> {
-        if self.args.len() != args.len() {
-            return Err("Arg number missmatch".to_string());
-        };
-        for (i, member) in args.into_iter().enumerate() {
-            context.reg_var(self.args.get(i).unwrap(), member);
+    pub fn name(&self) -> Ident<'a> {
+        self.name
+    }
+
+    /// Enters the function's scope with the arguments bound and hands back its body,
+    /// after the parameters that still have to be bound to their default.
+    /// Evaluating those and leaving the scope is up to the caller
+    pub fn call(
+        self: &Rc<Self>,
+        args: Vec<Member<'a>>,
+        context: &mut ProgContext<'a>,
+    ) -> Result<(Pending<'a>, Member<'a>), RispError> {
+        context.enter(self.name, self.captured.clone())?;
+        if self.local {
+            context.reg_member(self.name, Member::Fun(self.clone()));
+        }
+        let mut bound = Bound::default();
+        match self.params.bind(args, &mut bound) {
+            // the evaluator takes forms apart as it goes, each call gets its own copy of the body
+            Ok(()) => Ok((bound.commit(context), Member::clone(&self.expr))),
+            Err(problem) => {
+                context.pop_level();
+                Err(RispError::Arity(format!(
+                    "Bad arguments in call to {}: {problem}, expected {}",
+                    self.name,
+                    self.params.signature(self.name)
+                )))
+            }
         }
-        Ok(())
     }
+}
 
-    pub fn call(self, args: Vec<Member<'a>>, context: &mut ProgContext<'a>) -> OpResult<'a> {
-        context.next_level();
-        self.reg_args(args, context)?;
-        let res = interpret_sexpr(self.expr, context);
-        context.pop_level();
-        res
+/// How many arguments a builtin takes, checked before it's called
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    Exactly(usize),
+    AtLeast(usize),
+    /// Both bounds included
+    Between(usize, usize),
+}
+
+impl Arity {
+    pub fn allows(self, n: usize) -> bool {
+        match self {
+            Self::Exactly(m) => n == m,
+            Self::AtLeast(min) => n >= min,
+            Self::Between(min, max) => (min..=max).contains(&n),
+        }
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let plural = |n: usize| if n == 1 { "argument" } else { "arguments" };
+        match *self {
+            Self::Exactly(0) => write!(f, "no arguments"),
+            Self::Exactly(n) => write!(f, "{n} {}", plural(n)),
+            Self::AtLeast(n) => write!(f, "at least {n} {}", plural(n)),
+            Self::Between(min, max) => write!(f, "{min} to {max} arguments"),
+        }
+    }
+}
+
+/// Special form, handed its arguments unevaluated
+type FormFn<'a> = fn(
+    Ident<'a>,
+    VecDeque<Member<'a>>,
+    &mut Vec<Frame<'a>>,
+    &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError>;
+
+/// Builtin taking part in the evaluation, by pushing frames or unwinding them
+type ControlFn<'a> = fn(
+    Ident<'a>,
+    Vec<Member<'a>>,
+    &mut Vec<Frame<'a>>,
+    &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError>;
+
+/// Builtin computing a value from its arguments, handed the name it's called by
+/// so that related builtins can share one
+pub type ValueFn<'a> =
+    fn(&str, Vec<Member<'a>>, &mut ProgContext<'a>) -> Result<Member<'a>, RispError>;
+
+/// Closure a host embedding the interpreter registers, see [`Builtin::host`]
+pub type HostFn<'a> =
+    Rc<dyn Fn(Vec<Member<'a>>, &mut ProgContext<'a>) -> Result<Member<'a>, RispError> + 'a>;
+
+#[derive(Clone)]
+enum Native<'a> {
+    Form(FormFn<'a>),
+    Control(ControlFn<'a>),
+    Value(ValueFn<'a>),
+    Host(HostFn<'a>),
+}
+
+/// Function or special form implemented in Rust. Every builtin is bound in the root
+/// scope, where programs can redefine it, and can be shadowed like any other function
+pub struct Builtin<'a> {
+    pub name: Ident<'a>,
+    pub arity: Arity,
+    /// How it's called and what it does, for `doc`
+    pub doc: &'static str,
+    native: Native<'a>,
+}
+
+impl<'a> Builtin<'a> {
+    pub fn value(name: Ident<'a>, arity: Arity, doc: &'static str, fun: ValueFn<'a>) -> Self {
+        Self {
+            name,
+            arity,
+            doc,
+            native: Native::Value(fun),
+        }
+    }
+
+    /// Builtin running a closure, which unlike the other builtins can hold state of the host
+    pub fn host(name: Ident<'a>, arity: Arity, doc: &'static str, fun: HostFn<'a>) -> Self {
+        Self {
+            name,
+            arity,
+            doc,
+            native: Native::Host(fun),
+        }
+    }
+
+    fn control(name: Ident<'a>, arity: Arity, doc: &'static str, fun: ControlFn<'a>) -> Self {
+        Self {
+            name,
+            arity,
+            doc,
+            native: Native::Control(fun),
+        }
+    }
+
+    fn form(name: Ident<'a>, arity: Arity, doc: &'static str, fun: FormFn<'a>) -> Self {
+        Self {
+            name,
+            arity,
+            doc,
+            native: Native::Form(fun),
+        }
+    }
+
+    /// Gets its arguments unevaluated, and can't be called through `funcall` or `apply`
+    pub fn is_form(&self) -> bool {
+        matches!(self.native, Native::Form(_))
+    }
+
+    fn check_arity(&self, n: usize) -> Result<(), RispError> {
+        match self.arity.allows(n) {
+            true => Ok(()),
+            false => Err(RispError::Arity(format!(
+                "{} expects {}, got {n}",
+                self.name, self.arity
+            ))),
+        }
     }
 
-    fn get_and_call(
-        ident: Ident<'a>,
+    fn call(
+        &self,
         args: Vec<Member<'a>>,
+        frames: &mut Vec<Frame<'a>>,
         context: &mut ProgContext<'a>,
-    ) -> OpResult<'a> {
-        let fun = context
-            .get_fun(ident)
-            .ok_or(format!("Function {ident} not found"))?;
-        let args = args.into_iter().map(|m| m.resolve(context)).collect();
-        fun.call(args, context)
+    ) -> Result<Control<'a>, RispError> {
+        self.check_arity(args.len())?;
+        match &self.native {
+            Native::Form(_) => Err(RispError::Type(format!(
+                "{} is a special form, it can't be called as a function",
+                self.name
+            ))),
+            Native::Control(fun) => fun(self.name, args, frames, context),
+            Native::Value(fun) => Ok(Control::Value(fun(self.name, args, context)?)),
+            Native::Host(fun) => Ok(Control::Value(fun(args, context)?)),
+        }
+    }
+}
+
+// builtins are told apart by name, registering one replaces the one of the same name
+impl PartialEq for Builtin<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl fmt::Debug for Builtin<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#<builtin {}>", self.name)
     }
 }
 
 type Bindings<'a, T> = HashMap<Ident<'a>, T>;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Scope<'a> {
+    /// Tells apart the scopes a continuation shares with the stack it's restored onto
+    id: usize,
+    /// Function whose call opened this scope, `None` for the root and for blocks
+    fun: Option<Ident<'a>>,
     variables: Bindings<'a, Member<'a>>,
-    functions: Bindings<'a, Fun<'a>>,
+    /// A `Member::Fun`, or a `Member::Builtin` for the builtins in the root scope
+    functions: Bindings<'a, Member<'a>>,
+    /// What the closure whose call opened this scope captured,
+    /// shadowed by what the scope binds itself
+    captured: Rc<Env<'a>>,
 }
 
+/// Default limit on nested calls, tail calls don't count towards it
+pub const DEFAULT_MAX_DEPTH: usize = 100_000;
+
+/// Called with an error no handler or `catch` takes and the names of the restarts
+/// active where it was raised, possibly none, returns the index of the restart to invoke if any
+pub type Debugger<'a> = Box<dyn FnMut(&ErrorValue<'a>, &[Ident<'a>]) -> Option<usize> + 'a>;
+
 pub struct ProgContext<'a> {
     stack: Vec<Scope<'a>>,
+    max_depth: usize,
+    debugger: Option<Debugger<'a>>,
+    /// Last id handed out to a scope or `dynamic-wind`
+    last_id: usize,
+    /// Types defined with `defstruct` and variants defined with `deftype`, by name
+    types: Bindings<'a, Rc<StructType<'a>>>,
+    /// Variants of the sum types defined with `deftype`, in order
+    sums: Bindings<'a, Vec<Ident<'a>>>,
+    /// Where `print` and friends write, stdout by default
+    output: Box<dyn Write + 'a>,
+    /// Where warnings go, stderr by default
+    error_output: Box<dyn Write + 'a>,
+    /// Where `read-line` and `read-all` read, stdin by default
+    input: Box<dyn BufRead + 'a>,
+    trace_level: Level,
+    tracer: Option<Tracer<'a>>,
+    /// Granted by the host, no file builtin works without it
+    filesystem: Option<FsAccess>,
+    /// Granted by the host, `run` fails without it
+    processes: bool,
+    /// Every builtin registered, as registered even when a program redefines it
+    builtins: Bindings<'a, Rc<Builtin<'a>>>,
 }
 
-impl<'a> ProgContext<'a> {
-    pub fn default() -> Self {
-        Self {
+/// Names interned by every context, see [`ProgContext::intern`]
+static NAMES: LazyLock<Mutex<HashSet<&'static str>>> = LazyLock::new(Default::default);
+
+impl Default for ProgContext<'_> {
+    fn default() -> Self {
+        let mut context = Self {
             stack: vec![Scope::default()],
+            max_depth: DEFAULT_MAX_DEPTH,
+            debugger: None,
+            last_id: 0,
+            types: Bindings::new(),
+            sums: Bindings::new(),
+            output: Box::new(io::stdout()),
+            error_output: Box::new(io::stderr()),
+            input: Box::new(io::stdin().lock()),
+            trace_level: Level::Off,
+            tracer: None,
+            filesystem: None,
+            processes: false,
+            builtins: Bindings::new(),
+        };
+        for builtin in registry() {
+            context.register(builtin);
+        }
+        context
+    }
+}
+
+impl<'a> ProgContext<'a> {
+    /// Binds `builtin` in the root scope, in place of any builtin or function of the same
+    /// name defined before
+    pub fn register(&mut self, builtin: Builtin<'a>) {
+        let builtin = Rc::new(builtin);
+        self.stack[0]
+            .functions
+            .insert(builtin.name, Member::Builtin(builtin.clone()));
+        self.builtins.insert(builtin.name, builtin);
+    }
+
+    /// Every builtin registered, by name, for help and completion
+    pub fn builtins(&self) -> Vec<Rc<Builtin<'a>>> {
+        let mut builtins: Vec<_> = self.builtins.values().cloned().collect();
+        builtins.sort_by_key(|builtin| builtin.name);
+        builtins
+    }
+
+    /// Builtin as registered, whatever the program bound to its name since
+    fn builtin(&self, name: Ident<'a>) -> Result<Rc<Builtin<'a>>, RispError> {
+        self.builtins
+            .get(name)
+            .cloned()
+            .ok_or_else(|| RispError::Unbound(format!("Builtin {name} not found")))
+    }
+
+    /// Ident for a name that doesn't live as long as the context, like the functions
+    /// generated by a `defstruct` or the names of a program read from a file.
+    /// Values holding idents can outlive the context, so the names are leaked: each
+    /// distinct name once for the whole process, reused by every context after
+    fn intern(&mut self, name: &str) -> Ident<'a> {
+        let mut names = NAMES.lock().unwrap_or_else(PoisonError::into_inner);
+        match names.get(name) {
+            Some(ident) => ident,
+            None => {
+                let ident = Box::leak(name.to_string().into_boxed_str());
+                names.insert(ident);
+                ident
+            }
+        }
+    }
+
+    fn fresh_id(&mut self) -> usize {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn with_debugger(mut self, debugger: Debugger<'a>) -> Self {
+        self.debugger = Some(debugger);
+        self
+    }
+
+    /// Sends the output of the program to `output`, to capture it for example
+    pub fn with_output(mut self, output: impl Write + 'a) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    /// Sends the warnings of the program to `output` instead of stderr
+    pub fn with_error_output(mut self, output: impl Write + 'a) -> Self {
+        self.error_output = Box::new(output);
+        self
+    }
+
+    /// Feeds the program `input` instead of stdin
+    pub fn with_input(mut self, input: impl BufRead + 'a) -> Self {
+        self.input = Box::new(input);
+        self
+    }
+
+    /// Hands the events up to `level` to `tracer`
+    pub fn with_tracer(mut self, level: Level, tracer: Tracer<'a>) -> Self {
+        self.trace_level = level;
+        self.tracer = Some(tracer);
+        self
+    }
+
+    fn trace(&mut self, event: Event<'_, 'a>) {
+        if event.level() > self.trace_level {
+            return;
+        }
+        if let Some(tracer) = &mut self.tracer {
+            tracer(&event);
+        }
+    }
+
+    /// Lets the file builtins access the file system
+    pub fn with_filesystem(mut self, access: FsAccess) -> Self {
+        self.filesystem = Some(access);
+        self
+    }
+
+    pub fn filesystem(&self) -> Option<&FsAccess> {
+        self.filesystem.as_ref()
+    }
+
+    /// Lets `run` start processes
+    pub fn with_processes(mut self) -> Self {
+        self.processes = true;
+        self
+    }
+
+    pub fn processes(&self) -> bool {
+        self.processes
+    }
+
+    /// Defines the functions of the prelude, which programs can do without in a
+    /// minimal sandbox
+    pub fn with_prelude(mut self) -> Self {
+        prelude::load(&mut self);
+        self
+    }
+
+    /// Binds `*argv*` to the arguments the program was given, as a list of strings
+    pub fn with_args(mut self, args: impl IntoIterator<Item = String>) -> Self {
+        self.reg_var(
+            "*argv*",
+            Member::List(args.into_iter().map(Member::String).collect()),
+        );
+        self
+    }
+
+    pub fn output(&mut self) -> &mut dyn Write {
+        &mut self.output
+    }
+
+    pub fn error_output(&mut self) -> &mut dyn Write {
+        &mut self.error_output
+    }
+
+    pub fn input(&mut self) -> &mut dyn BufRead {
+        &mut self.input
+    }
+
+    /// Current number of nested calls, counting the scopes of the `match` clauses and
+    /// the like they are in
+    pub fn depth(&self) -> usize {
+        self.stack.len() - 1
+    }
+
+    pub fn next_level(&mut self, fun: Ident<'a>) -> Result<(), RispError> {
+        if self.depth() >= self.max_depth {
+            return Err(RispError::StackOverflow {
+                depth: self.max_depth,
+                frames: self.call_stack(),
+            });
+        }
+        let id = self.fresh_id();
+        self.stack.push(Scope {
+            id,
+            fun: Some(fun),
+            ..Default::default()
+        });
+        Ok(())
+    }
+
+    /// Opens a scope for what a `match` clause or the like binds, left like the scope of
+    /// a call once the `Frame::Call` pushed with it gets a value
+    fn open_block(&mut self) {
+        let id = self.fresh_id();
+        self.stack.push(Scope {
+            id,
+            ..Default::default()
+        });
+    }
+
+    /// Opens a scope for `fun` with what a closure captured already bound
+    fn enter(&mut self, fun: Ident<'a>, env: Rc<Env<'a>>) -> Result<(), RispError> {
+        self.next_level(fun)?;
+        self.stack.last_mut().unwrap().captured = env;
+        Ok(())
+    }
+
+    /// Index of the scope of the innermost call, 0 at the top level
+    fn call_base(&self) -> usize {
+        self.stack
+            .iter()
+            .rposition(|scope| scope.fun.is_some())
+            .unwrap_or(0)
+    }
+
+    /// Scopes names are looked up in, innermost first: those of the innermost call and
+    /// the blocks in it, then the root. The scopes of the calls it's in aren't visible
+    fn visible(&self) -> impl Iterator<Item = &Scope<'a>> {
+        let base = self.call_base();
+        let root = if base > 0 { &self.stack[..1] } else { &[] };
+        self.stack[base..].iter().rev().chain(root)
+    }
+
+    /// What the innermost call and the blocks in it bind, for a closure made there.
+    /// Nothing at the top level, where everything stays visible from the root
+    fn local_env(&self) -> Env<'a> {
+        let mut env = Env::default();
+        for scope in &self.stack[self.call_base().max(1)..] {
+            env.variables.extend(
+                (scope.captured.variables.iter())
+                    .chain(&scope.variables)
+                    .map(|(k, v)| (*k, v.clone())),
+            );
+            env.functions.extend(
+                (scope.captured.functions.iter())
+                    .chain(&scope.functions)
+                    .map(|(k, v)| (*k, v.clone())),
+            );
         }
+        env
     }
 
-    pub fn next_level(&mut self) {
-        self.stack.push(Scope::default());
+    /// Copy of every scope above the root, for a continuation to restore later
+    fn snapshot(&self) -> Vec<Scope<'a>> {
+        self.stack[1..].to_vec()
+    }
+
+    /// Puts back the scopes of a snapshot, those still on the stack are kept as they are now
+    fn restore(&mut self, snapshot: &[Scope<'a>]) {
+        let shared = self.stack[1..]
+            .iter()
+            .zip(snapshot)
+            .take_while(|(scope, saved)| scope.id == saved.id)
+            .count();
+        self.stack.truncate(1 + shared);
+        self.stack.extend_from_slice(&snapshot[shared..]);
     }
 
     pub fn pop_level(&mut self) {
         self.stack.pop();
     }
     pub fn reg_fun(&mut self, ident: Ident<'a>, fun: Fun<'a>) {
+        self.reg_member(ident, Member::Fun(Rc::new(fun)));
+    }
+
+    fn reg_member(&mut self, ident: Ident<'a>, fun: Member<'a>) {
         self.stack.last_mut().unwrap().functions.insert(ident, fun);
     }
     pub fn reg_var(&mut self, ident: Ident<'a>, value: Member<'a>) {
@@ -80,46 +563,91 @@ impl<'a> ProgContext<'a> {
             .insert(ident, value);
     }
 
-    pub fn get_fun(&self, ident: Ident<'a>) -> Option<Fun<'a>> {
-        let layer = self.stack.len();
-        for i in (0..layer).rev() {
-            let scope = self.stack.get(i).unwrap();
-            match scope.functions.get(&ident).cloned() {
-                Some(fun) => return Some(fun),
-                None => continue,
-            }
-        }
-        None
+    /// Function or builtin bound to `ident` in the innermost visible scope that binds it
+    pub fn get_fun(&self, ident: Ident<'a>) -> Option<Member<'a>> {
+        self.visible()
+            .find_map(|scope| {
+                (scope.functions.get(ident)).or_else(|| scope.captured.functions.get(ident))
+            })
+            .cloned()
     }
 
-    pub fn get_var(&self, ident: Ident<'a>) -> Member<'a> {
-        let layer = self.stack.len();
-        for i in (0..layer).rev() {
-            let scope = self.stack.get(i).unwrap();
-            match scope.variables.get(&ident) {
-                Some(var) => return var.clone(),
-                None => continue,
-            }
-        }
-        panic!("Variable {ident} not found");
+    pub fn get_var(&self, ident: &str) -> Result<Member<'a>, RispError> {
+        self.visible()
+            .find_map(|scope| {
+                (scope.variables.get(ident)).or_else(|| scope.captured.variables.get(ident))
+            })
+            .cloned()
+            .ok_or_else(|| RispError::Unbound(format!("Variable {ident} not found")))
+    }
+
+    /// Functions currently being called, innermost first
+    pub fn call_stack(&self) -> Vec<String> {
+        self.stack
+            .iter()
+            .rev()
+            .filter_map(|scope| scope.fun.map(str::to_string))
+            .collect()
     }
 }
 
-pub fn interpret<'a>(tree: AST<'a>) {
-    let mut context = ProgContext::default();
+/// Evaluates every top level form in order and returns the value of the last one,
+/// `()` for an empty program. Forms after an `exit` or an unhandled error aren't evaluated
+pub fn eval_program<'a>(
+    tree: AST<'a>,
+    context: &mut ProgContext<'a>,
+) -> Result<Member<'a>, RispError> {
+    let mut last = Member::Unit;
     for line in tree.prog {
-        println!("Interpreting {line:?}");
-        match interpret_sexpr(line, &mut context) {
-            Ok(ans) => println!("{ans:?}"),
-            Err(e) => {
-                println!("{e}");
-                exit(1);
+        context.trace(Event::Form(&line));
+        last = interpret_sexpr(line, context)?;
+        context.trace(Event::Value(&last));
+    }
+    Ok(last)
+}
+
+/// Program parsed from a source that doesn't live as long as `context`, with its names
+/// interned so that it no longer borrows the source
+pub fn intern_program<'a>(tree: AST<'_>, context: &mut ProgContext<'a>) -> AST<'a> {
+    AST {
+        prog: tree
+            .prog
+            .into_iter()
+            .map(|form| intern_sexpr(form, context))
+            .collect(),
+    }
+}
+
+fn intern_sexpr<'a>(mut sexpr: SExpr<'_>, context: &mut ProgContext<'a>) -> SExpr<'a> {
+    // the expressions being interned, innermost last, with the members left to intern
+    let mut open = vec![(mem::take(&mut sexpr.members).into_iter(), VecDeque::new())];
+    loop {
+        let member = match open.last_mut().unwrap().0.next() {
+            Some(Member::SExpr(mut s)) => {
+                open.push((mem::take(&mut s.members).into_iter(), VecDeque::new()));
+                continue;
+            }
+            Some(Member::Ident(name)) => Member::Ident(context.intern(name)),
+            Some(Member::Keyword(name)) => Member::Keyword(context.intern(name)),
+            Some(Member::String(s)) => Member::String(s),
+            Some(Member::Char(c)) => Member::Char(c),
+            Some(Member::NumI(n)) => Member::NumI(n),
+            Some(Member::Bool(b)) => Member::Bool(b),
+            Some(Member::Unit) => Member::Unit,
+            Some(value) => unreachable!("the parser doesn't produce {value}"),
+            None => {
+                let (_, members) = open.pop().unwrap();
+                match open.is_empty() {
+                    true => return SExpr { members },
+                    false => Member::SExpr(SExpr { members }),
+                }
             }
         };
+        open.last_mut().unwrap().1.push_back(member);
     }
 }
 
-type OpResult<'a> = Result<Member<'a>, String>;
+type OpResult<'a> = Result<Member<'a>, RispError>;
 
 fn define_fun<'a>(mut p_args: VecDeque<Member<'a>>, context: &mut ProgContext<'a>) -> OpResult<'a> {
     let ident = p_args
@@ -130,83 +658,2809 @@ fn define_fun<'a>(mut p_args: VecDeque<Member<'a>>, context: &mut ProgContext<'a
     let args = p_args
         .pop_front()
         .ok_or("def needs 3 arguments (def {name} ({args...}) ({function}))".to_string())?
-        .as_ident_list()
-        .ok_or("def needs argument 2 to be ident list".to_string())?;
+        .as_sexpr()
+        .ok_or("def needs argument 2 to be a parameter list".to_string())?;
     let expr = p_args
         .pop_front()
-        .ok_or("def needs 3 arguments (def {name} ({args...}) ({function}))".to_string())?
-        .as_sexpr()
-        .ok_or("def needs argument 3 to be SExpr".to_string())?;
-    assert!(
-        p_args.len() == 0,
-        "def needs 3 arguments (def {{name}} ({{args...}}) ({{function}}))"
-    );
-    let fun = Fun { args, expr };
-    context.reg_fun(&ident, fun);
+        .ok_or("def needs 3 arguments (def {name} ({args...}) ({function}))".to_string())?;
+    if !p_args.is_empty() {
+        return Err("def needs 3 arguments (def {name} ({args...}) ({function}))".into());
+    }
+    let fun = Fun {
+        name: ident,
+        params: ListPattern::parse(args)?,
+        expr: Rc::new(expr),
+        captured: Rc::new(context.local_env()),
+        local: context.depth() > 0,
+    };
+    context.reg_fun(ident, fun);
     Ok(Member::Unit)
 }
 
-pub fn interpret_sexpr<'a>(
-    mut sexpr: SExpr<'a>,
-    mut context: &mut ProgContext<'a>,
+/// `(defstruct point x y)` defines `make-point` taking the fields in order,
+/// the predicate `point?` and an accessor like `point-x` for each field
+fn define_struct<'a>(p_args: VecDeque<Member<'a>>, context: &mut ProgContext<'a>) -> OpResult<'a> {
+    let error = || "defstruct is written (defstruct {name} {field...})".to_string();
+    let mut idents = p_args.into_iter().map(Member::as_ident);
+    let name = idents.next().flatten().ok_or_else(error)?;
+    let fields = idents.collect::<Option<Vec<_>>>().ok_or_else(error)?;
+    let constructor = context.intern(&format!("make-{name}"));
+    let ty = StructType {
+        name,
+        fields,
+        sum: None,
+    };
+    define_record(ty, constructor, context)?;
+    Ok(Member::Unit)
+}
+
+/// `(deftype shape (circle r) (rect w h))` defines each variant like a struct whose
+/// constructor is named after it, `circle` and `rect`, and the predicate `shape?`
+fn define_type<'a>(
+    mut p_args: VecDeque<Member<'a>>,
+    context: &mut ProgContext<'a>,
 ) -> OpResult<'a> {
-    let first = sexpr.members.pop_front().unwrap().as_ident();
-    if first.is_some() {
-        let fun_name = first.unwrap();
-        // is a function call
-        // context level isnt raised for let and def, they update the parent context
-        // function.call raises its context
-        let result = match fun_name {
-            "def" => define_fun(sexpr.members, &mut context),
-            "let" => {
-                let ident = sexpr
-                    .members
-                    .pop_front()
-                    .ok_or(format!("let requires 2 arguments"))?
-                    .as_ident()
-                    .ok_or(format!("let requires argument 1 to be ident"))?;
-                let value = sexpr
-                    .members
-                    .pop_front()
-                    .ok_or(format!("let requires 2 arguments"))?;
-                if sexpr.members.len() != 0 {
-                    return Err(format!("let requires 2 arguments"));
-                }
-                context.reg_var(ident, value.clone());
-                Ok(value)
+    let error = || "deftype is written (deftype {name} ({variant} {field...})...)".to_string();
+    let name = p_args
+        .pop_front()
+        .and_then(Member::as_ident)
+        .ok_or_else(error)?;
+    let mut variants = Vec::new();
+    for variant in p_args {
+        let mut idents = match variant {
+            // variants without fields may be written without parentheses
+            Member::Ident(variant) => vec![Some(variant)].into_iter(),
+            Member::SExpr(s) => s
+                .into_members()
+                .into_iter()
+                .map(Member::as_ident)
+                .collect::<Vec<_>>()
+                .into_iter(),
+            _ => return Err(error().into()),
+        };
+        let variant = idents.next().flatten().ok_or_else(error)?;
+        let fields = idents.collect::<Option<Vec<_>>>().ok_or_else(error)?;
+        let ty = StructType {
+            name: variant,
+            fields,
+            sum: Some(name),
+        };
+        define_record(ty, variant, context)?;
+        variants.push(variant);
+    }
+    context.sums.insert(name, variants);
+
+    let predicate = context.intern(&format!("{name}?"));
+    let fun = Fun {
+        name: predicate,
+        params: ListPattern::parse(SExpr::new(vec![Member::Ident("value")]))?,
+        expr: Rc::new(Member::SExpr(SExpr::new(vec![
+            Member::Ident("%sum?"),
+            Member::Keyword(name),
+            Member::Ident("value"),
+        ]))),
+        captured: Rc::default(),
+        local: false,
+    };
+    context.reg_fun(predicate, fun);
+    Ok(Member::Unit)
+}
+
+/// Registers the type along with its constructor, predicate and accessors
+fn define_record<'a>(
+    ty: StructType<'a>,
+    constructor: Ident<'a>,
+    context: &mut ProgContext<'a>,
+) -> Result<(), RispError> {
+    let name = ty.name;
+    let fields = ty.fields.clone();
+    context.types.insert(name, Rc::new(ty));
+
+    // the generated functions call hidden builtins with the type's name
+    let call = |builtin: Ident<'a>, args: Vec<Member<'a>>| {
+        let mut members = vec![Member::Ident(builtin), Member::Keyword(name)];
+        members.extend(args);
+        Member::SExpr(SExpr::new(members))
+    };
+    let idents = |names: &[Ident<'a>]| names.iter().map(|n| Member::Ident(n)).collect::<Vec<_>>();
+    let fun = Fun {
+        name: constructor,
+        params: ListPattern::parse(SExpr::new(idents(&fields)))?,
+        expr: Rc::new(call("%make-struct", idents(&fields))),
+        captured: Rc::default(),
+        local: false,
+    };
+    context.reg_fun(constructor, fun);
+
+    let predicate = context.intern(&format!("{name}?"));
+    let fun = Fun {
+        name: predicate,
+        params: ListPattern::parse(SExpr::new(idents(&["value"])))?,
+        expr: Rc::new(call("%struct?", idents(&["value"]))),
+        captured: Rc::default(),
+        local: false,
+    };
+    context.reg_fun(predicate, fun);
+    for field in fields {
+        let accessor = context.intern(&format!("{name}-{field}"));
+        let fun = Fun {
+            name: accessor,
+            params: ListPattern::parse(SExpr::new(idents(&["value"])))?,
+            expr: Rc::new(call(
+                "%struct-get",
+                vec![Member::Ident("value"), Member::Keyword(field)],
+            )),
+            captured: Rc::default(),
+            local: false,
+        };
+        context.reg_fun(accessor, fun);
+    }
+    Ok(())
+}
+
+/// What the evaluator does next
+enum Control<'a> {
+    /// Evaluate an expression
+    Eval(Member<'a>),
+    /// Hand a value to the innermost pending frame
+    Value(Member<'a>),
+    /// Pop frames until the exit reaches its destination
+    Unwind(Exit<'a>),
+}
+
+/// Non-local exit in progress
+#[derive(Debug, Clone)]
+enum Exit<'a> {
+    /// Value thrown until a `catch` takes it
+    Throw(Member<'a>),
+    /// Jump to the `restart-case` frame at index `target` and run one of its restarts
+    Restart {
+        target: usize,
+        restart: Restart<'a>,
+        args: Vec<Member<'a>>,
+    },
+    /// Leave everything above the `call/ec` frame at index `target` and hand it `value`
+    Escape { target: usize, value: Member<'a> },
+    /// Swap the frames for those of a continuation once the ones above index `keep`,
+    /// which the continuation doesn't share, are gone
+    Jump {
+        continuation: Rc<Continuation<'a>>,
+        keep: usize,
+        value: Member<'a>,
+    },
+    /// Leave the program with an exit code once every frame is gone
+    Quit(i32),
+}
+
+/// Code run when control enters or leaves a `dynamic-wind`
+#[derive(Debug, Clone)]
+enum Thunk<'a> {
+    /// Expression, like the body of a `finally`
+    Expr(Member<'a>),
+    /// Function called without arguments
+    Call(Member<'a>),
+}
+
+impl<'a> Thunk<'a> {
+    /// Frame running the thunk once the previous value is discarded
+    fn frame(self) -> Frame<'a> {
+        match self {
+            Self::Expr(expr) => Frame::Then(expr),
+            Self::Call(fun) => Frame::Invoke(fun),
+        }
+    }
+}
+
+/// Rest of a computation, as a value that can be called
+pub struct Continuation<'a>(Captured<'a>);
+
+enum Captured<'a> {
+    /// Taken by `call/cc`, can be called any number of times, even once it returned
+    Full {
+        frames: Vec<Frame<'a>>,
+        scopes: Vec<Scope<'a>>,
+    },
+    /// Taken by `call/ec`, only valid while the `call/ec` with this id is running
+    Escape(usize),
+    /// Taken by `perform`, what was left of the `handle` it was performed in,
+    /// gone once resumed
+    Effect(RefCell<Option<Suspended<'a>>>),
+}
+
+/// Frames from a `handle` up to a `perform`, with the scopes opened by their calls
+struct Suspended<'a> {
+    frames: Vec<Frame<'a>>,
+    scopes: Vec<Scope<'a>>,
+}
+
+impl fmt::Debug for Continuation<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Captured::Full { .. } => write!(f, "#<continuation>"),
+            Captured::Escape(id) => write!(f, "#<escape continuation {id}>"),
+            Captured::Effect(_) => write!(f, "#<effect continuation>"),
+        }
+    }
+}
+
+impl PartialEq for Continuation<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+/// How a condition is treated when no handler takes it
+#[derive(Debug, Clone, Copy)]
+enum Severity {
+    /// `signal` returns `()`
+    Signal,
+    /// `warn` prints the condition and returns `()`
+    Warning,
+    /// `error` invokes the debugger, then throws the condition
+    Error,
+}
+
+/// Work left pending while a subexpression is evaluated.
+/// Risp calls push frames instead of recursing on the Rust stack
+#[derive(Debug, Clone)]
+enum Frame<'a> {
+    /// Arguments of a call, evaluated left to right
+    Args {
+        fun: Callee<'a>,
+        done: Vec<Member<'a>>,
+        rest: VecDeque<Member<'a>>,
+    },
+    /// Head of a call that isn't a name, the arguments are evaluated once it has a value
+    Head(VecDeque<Member<'a>>),
+    /// Value waiting to be bound in the current scope, by `let` or a parameter default
+    Let(Pattern<'a>),
+    /// Previous value is discarded and this is evaluated instead
+    Then(Member<'a>),
+    /// Previous value is discarded and this is handed on instead
+    Const(Member<'a>),
+    /// Condition of an `if` waiting to pick a branch
+    If {
+        then: Member<'a>,
+        otherwise: Member<'a>,
+    },
+    /// Value being matched against the clauses of a `match`
+    Match(VecDeque<Clause<'a>>),
+    /// Value dispatched on by a `case-type`
+    CaseType(Vec<CaseClause<'a>>),
+    /// Guard of a clause that matched `value`, the remaining clauses are tried if it fails
+    Guard {
+        value: Member<'a>,
+        body: Member<'a>,
+        clauses: VecDeque<Clause<'a>>,
+    },
+    /// Body of a `try`, thrown values are checked against its `catch` clauses
+    Catch(Vec<Catch<'a>>),
+    /// Body of a `dynamic-wind`, or of a `try` whose `finally` is `after`.
+    /// `after` runs whenever control leaves it, `before` whenever a continuation jumps back in
+    Wind {
+        id: usize,
+        before: Option<Thunk<'a>>,
+        after: Thunk<'a>,
+    },
+    /// `before` of a `dynamic-wind` running, `thunk` is called once it's done
+    Winding {
+        before: Thunk<'a>,
+        thunk: Member<'a>,
+        after: Thunk<'a>,
+    },
+    /// Body of a `call/ec`, escape continuations with the same id return here
+    Escape(usize),
+    /// Previous value is discarded and this function is called without arguments
+    Invoke(Member<'a>),
+    /// Step of a lazy sequence being computed, the value is what the sequence is realized as
+    Realize(Rc<Seq<'a>>),
+    /// Body of a generator, `yield` suspends everything above
+    Generate,
+    /// Call to the function of an `iterate`, the value is its next element
+    Iterated(Member<'a>),
+    /// Call to the predicate of a `take-while` on its next element
+    TakeWhile {
+        pred: Member<'a>,
+        first: Member<'a>,
+        rest: Member<'a>,
+    },
+    /// Builtin called again once the sequence it was waiting on is realized
+    Retry {
+        fun: Ident<'a>,
+        args: Vec<Member<'a>>,
+    },
+    /// Builtin taking a whole sequence, `rest` still has to be walked
+    Collect {
+        fun: Ident<'a>,
+        done: Vec<Member<'a>>,
+        rest: Member<'a>,
+    },
+    /// Body of a `handle`, effects performed in it are handled by the first matching clause
+    Handle(Vec<Effect<'a>>),
+    /// Effect handler running. If it's left without resuming the `perform`,
+    /// the `after` of each `dynamic-wind` or `finally` the computation was in still runs
+    Handling(Rc<Continuation<'a>>),
+    /// Carries on with the exit once the `finally` that interrupted it is done
+    Resume(Exit<'a>),
+    /// Handler expressions of a `handler-bind`, evaluated before its body
+    HandlerBind {
+        done: Vec<Handler<'a>>,
+        kind: Option<Ident<'a>>,
+        rest: VecDeque<(Option<Ident<'a>>, Member<'a>)>,
+        body: Member<'a>,
+    },
+    /// Body of a `handler-bind`, its handlers are called with conditions signalled inside
+    Handlers(Vec<Handler<'a>>),
+    /// Handler running for a condition, if it returns the search carries on with
+    /// the clause after `clause` of the `handler-bind` at index `cluster`.
+    /// While it runs, that `handler-bind` and the ones above it are skipped
+    Signal {
+        condition: Member<'a>,
+        severity: Severity,
+        cluster: usize,
+        clause: usize,
+    },
+    /// Body of a `restart-case`, `invoke-restart` jumps here
+    Restarts(Vec<Restart<'a>>),
+    /// Body of a function call or of a block, its scope is left once the body has a value
+    Call,
+}
+
+/// What a call calls
+#[derive(Debug, Clone)]
+enum Callee<'a> {
+    /// Looked up once the arguments have a value
+    Name(Ident<'a>),
+    /// Value of the head of the call, `((lambda (x) x) 1)`
+    Value(Member<'a>),
+}
+
+impl<'a> Callee<'a> {
+    /// Name the call is traced with
+    fn name(&self) -> Ident<'a> {
+        match self {
+            Self::Name(name) => name,
+            Self::Value(Member::Fun(fun)) => fun.name,
+            Self::Value(Member::Builtin(builtin)) => builtin.name,
+            Self::Value(_) => "continuation",
+        }
+    }
+}
+
+pub fn interpret_sexpr<'a>(sexpr: SExpr<'a>, context: &mut ProgContext<'a>) -> OpResult<'a> {
+    let mut frames = Vec::new();
+    run(Control::Eval(Member::SExpr(sexpr)), &mut frames, context)
+}
+
+fn run<'a>(
+    mut control: Control<'a>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> OpResult<'a> {
+    loop {
+        let step = match control {
+            Control::Eval(member) => eval(member, frames, context),
+            Control::Value(value) => match frames.pop() {
+                Some(frame) => resume(frame, value, frames, context),
+                None => return Ok(value),
+            },
+            Control::Unwind(exit) => match unwind(exit, frames, context) {
+                Ok(control) => Ok(control),
+                Err(uncaught) => return Err(uncaught),
+            },
+        };
+        control = match step {
+            Ok(control) => control,
+            // `exit` isn't an error, no handler or `catch` gets to stop it
+            Err(RispError::Exit(code)) => Control::Unwind(Exit::Quit(code)),
+            // errors raised by the evaluator are signalled like any other error
+            Err(e) => {
+                let error = match e {
+                    // thrown by a builtin, like a host function, with a kind of its own
+                    RispError::Thrown { kind, message } => {
+                        ErrorValue::new(context.intern(&kind), message, context.call_stack())
+                    }
+                    e => ErrorValue::from_error(e, context.call_stack()),
+                };
+                let condition = Member::Error(Rc::new(error));
+                let top = frames.len();
+                signal(condition.clone(), Severity::Error, top, 0, frames, context)
+                    .unwrap_or(Control::Unwind(Exit::Throw(condition)))
             }
-            "+" => fun_std_sum(sexpr.members.into(), context),
-            "*" => fun_std_mul(sexpr.members.into(), context),
-            _ => Fun::get_and_call(fun_name, sexpr.members.into(), context),
         };
-        return result;
-    } else {
-        // is a list
-        context.next_level();
-        context.pop_level();
-        todo!();
     }
 }
 
-fn fun_std_sum<'a>(args: Vec<Member<'a>>, context: &mut ProgContext<'a>) -> OpResult<'a> {
-    context.next_level();
-    let mut args: Vec<Member<'a>> = args.into_iter().map(|arg| arg.resolve(context)).collect();
-    println!("std::sum {args:?}");
-    let mut res = args.pop().ok_or(format!("Sum called with no arguments"))?;
-    for rhs in args {
-        res = match res {
-            Member::Ident(_) => panic!("Attempted to add to unresolved ident"),
-            Member::String(s) => Member::String(s + &rhs.into_string_value(context)),
-            Member::NumI(n) => Member::NumI(n + rhs.into_inum_value(context)),
-            Member::Unit => res,
-            Member::SExpr(_) => unreachable!(),
+/// Pops frames until the exit reaches its destination, fails with the thrown value
+/// if no `catch` takes it, or with [`RispError::Exit`] once a `Quit` is out of frames
+fn unwind<'a>(
+    exit: Exit<'a>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    loop {
+        if let Exit::Jump { keep, .. } = &exit {
+            if frames.len() == *keep {
+                return Ok(jump(exit, frames, context));
+            }
+        }
+        let Some(frame) = frames.pop() else {
+            break;
         };
+        match (frame, &exit) {
+            (Frame::Escape(_), Exit::Escape { target, .. }) if frames.len() == *target => {
+                let Exit::Escape { value, .. } = exit else {
+                    unreachable!()
+                };
+                return Ok(Control::Value(value));
+            }
+            (Frame::Restarts(_), Exit::Restart { target, .. }) if frames.len() == *target => {
+                let Exit::Restart { restart, args, .. } = exit else {
+                    unreachable!()
+                };
+                return restart.enter(args, frames, context);
+            }
+            (Frame::Call, _) => context.pop_level(),
+            (Frame::Catch(catches), Exit::Throw(value)) => {
+                if let Some(catch) = catches.into_iter().find(|c| c.catches(value)) {
+                    if let Some(ident) = catch.ident {
+                        // bound for the body of the catch only
+                        context.open_block();
+                        context.reg_var(ident, value.clone());
+                        frames.push(Frame::Call);
+                    }
+                    return Ok(Control::Eval(catch.body));
+                }
+            }
+            (Frame::Wind { after, .. }, _) => {
+                frames.push(Frame::Resume(exit));
+                frames.push(after.frame());
+                return Ok(Control::Value(Member::Unit));
+            }
+            (Frame::Realize(seq), _) => seq.abort(),
+            (Frame::Handling(continuation), _) => {
+                let afters = abandon(&continuation);
+                if !afters.is_empty() {
+                    frames.push(Frame::Resume(exit));
+                    frames.extend(afters);
+                    return Ok(Control::Value(Member::Unit));
+                }
+            }
+            _ => (),
+        }
     }
-    context.pop_level();
-    Ok(res)
+    Err(match exit {
+        Exit::Throw(Member::Error(e)) => e.error.clone(),
+        Exit::Throw(value) => RispError::Thrown {
+            kind: "value".to_string(),
+            message: format!("{value}"),
+        },
+        Exit::Restart { restart, .. } => {
+            format!("Restart :{} is no longer active", restart.name).into()
+        }
+        Exit::Escape { .. } | Exit::Jump { .. } => "Continuation is no longer active".into(),
+        Exit::Quit(code) => RispError::Exit(code),
+    })
+}
+
+/// Installs the frames and scopes of a continuation, running the `before` of each
+/// `dynamic-wind` it enters that isn't on the stack already
+fn jump<'a>(
+    exit: Exit<'a>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Control<'a> {
+    let Exit::Jump {
+        continuation,
+        value,
+        ..
+    } = exit
+    else {
+        unreachable!()
+    };
+    let Captured::Full {
+        frames: saved,
+        scopes,
+    } = &continuation.0
+    else {
+        unreachable!()
+    };
+    let entered = winds(frames).count();
+    frames.clear();
+    frames.extend_from_slice(saved);
+    context.restore(scopes);
+    let befores: Vec<Thunk<'a>> = winds(saved)
+        .skip(entered)
+        .filter_map(|(_, before)| before.cloned())
+        .collect();
+    frames.push(Frame::Const(value));
+    for before in befores.into_iter().rev() {
+        frames.push(before.frame());
+    }
+    Control::Value(Member::Unit)
+}
+
+/// Id and `before` of every `dynamic-wind` among the frames, outermost first
+fn winds<'f, 'a>(frames: &'f [Frame<'a>]) -> impl Iterator<Item = (usize, Option<&'f Thunk<'a>>)> {
+    frames.iter().filter_map(|frame| match frame {
+        Frame::Wind { id, before, .. } => Some((*id, before.as_ref())),
+        _ => None,
+    })
+}
+
+/// Calls a continuation with `value`, jumping to where it was captured
+fn resume_continuation<'a>(
+    continuation: Rc<Continuation<'a>>,
+    value: Member<'a>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    match &continuation.0 {
+        Captured::Effect(suspended) => {
+            let suspended = suspended
+                .borrow_mut()
+                .take()
+                .ok_or("Effect continuations can only be resumed once")?;
+            frames.extend(suspended.frames);
+            context.stack.extend(suspended.scopes);
+            Ok(Control::Value(value))
+        }
+        Captured::Escape(id) => {
+            let target = frames
+                .iter()
+                .rposition(|frame| matches!(frame, Frame::Escape(e) if e == id))
+                .ok_or("Escape continuation called after its call/ec returned")?;
+            Ok(Control::Unwind(Exit::Escape { target, value }))
+        }
+        Captured::Full { frames: saved, .. } => {
+            // frames up to the innermost dynamic-wind both stacks share are kept,
+            // the `after` of every other one is run on the way out
+            let shared = winds(frames)
+                .zip(winds(saved))
+                .take_while(|((current, _), (target, _))| current == target)
+                .count();
+            let keep = match shared {
+                0 => 0,
+                n => {
+                    frames
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, frame)| matches!(frame, Frame::Wind { .. }))
+                        .nth(n - 1)
+                        .unwrap()
+                        .0
+                        + 1
+                }
+            };
+            Ok(Control::Unwind(Exit::Jump {
+                continuation,
+                keep,
+                value,
+            }))
+        }
+    }
+}
+
+/// Suspends the frames up to the innermost `handle` with a clause for the effect,
+/// and calls that clause in place of the `handle`
+fn perform<'a>(
+    args: Vec<Member<'a>>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    let mut args = args.into_iter();
+    let Some(Member::Keyword(name)) = args.next() else {
+        return Err(RispError::Type("perform requires an effect keyword".into()));
+    };
+    let (index, effect) = frames
+        .iter()
+        .enumerate()
+        .rev()
+        .find_map(|(index, frame)| match frame {
+            Frame::Handle(effects) => effects
+                .iter()
+                .find(|effect| effect.name == name)
+                .map(|effect| (index, effect.fun.clone())),
+            _ => None,
+        })
+        .ok_or_else(|| RispError::Unbound(format!("No handler for effect :{name}")))?;
+    let continuation = suspend(index, frames, context);
+    frames.push(Frame::Handling(continuation.clone()));
+    let mut args: Vec<Member<'a>> = args.collect();
+    args.insert(0, Member::Continuation(continuation));
+    call_value(Member::Fun(effect), args, frames, context)
+}
+
+/// Takes the frames from `index` up, with the scopes their calls opened,
+/// as a continuation that can be resumed once
+fn suspend<'a>(
+    index: usize,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Rc<Continuation<'a>> {
+    let frames = frames.split_off(index);
+    let calls = frames
+        .iter()
+        .filter(|frame| matches!(frame, Frame::Call))
+        .count();
+    let scopes = context.stack.split_off(context.stack.len() - calls);
+    Rc::new(Continuation(Captured::Effect(RefCell::new(Some(
+        Suspended { frames, scopes },
+    )))))
+}
+
+/// Computes the next cell of a sequence whose step runs code
+fn realize<'a>(
+    seq: Rc<Seq<'a>>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    let step = seq.start()?;
+    frames.push(Frame::Realize(seq));
+    match step {
+        Step::Expr { body, env } => {
+            context.enter("lazy-seq", Rc::new(env))?;
+            frames.push(Frame::Call);
+            Ok(Control::Eval(body))
+        }
+        Step::Generator { body, env } => {
+            frames.push(Frame::Generate);
+            context.enter("generator", Rc::new(env))?;
+            frames.push(Frame::Call);
+            Ok(Control::Eval(body))
+        }
+        Step::Resume(generator) => resume_continuation(generator, Member::Unit, frames, context),
+        Step::Iterate { fun, value } => {
+            frames.push(Frame::Iterated(fun.clone()));
+            call_value(fun, vec![value], frames, context)
+        }
+        Step::TakeWhile { pred, seq } => match seq::next(&seq)? {
+            Some(Next::Cons(first, rest)) => {
+                frames.push(Frame::TakeWhile {
+                    pred: pred.clone(),
+                    first: first.clone(),
+                    rest,
+                });
+                call_value(pred, vec![first], frames, context)
+            }
+            _ => unreachable!("take-while is only realized once its sequence is"),
+        },
+        Step::Range { .. } | Step::Take { .. } | Step::Drop { .. } => {
+            unreachable!("realized without running code")
+        }
+    }
+}
+
+/// `(yield value)`, makes `value` the next one of the innermost generator and suspends it
+fn fun_std_yield<'a>(
+    args: Vec<Member<'a>>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    let [value] = <[Member; 1]>::try_from(args)
+        .map_err(|_| RispError::Arity("yield takes a single value".into()))?;
+    let index = frames
+        .iter()
+        .rposition(|frame| matches!(frame, Frame::Generate))
+        .ok_or("yield can only be used in a generator")?;
+    let generator = suspend(index, frames, context);
+    let rest = Seq::new(Step::Resume(generator));
+    Ok(Control::Value(Member::Seq(Rc::new(Seq::cons(
+        value,
+        Member::Seq(Rc::new(rest)),
+    )))))
+}
+
+/// Builtins looking at the front of a sequence, realizing it first if needed.
+/// `front` gets the first value and the rest, `None` when the sequence is empty
+fn fun_std_front<'a>(
+    fun_name: Ident<'a>,
+    args: Vec<Member<'a>>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+    front: fn(Option<(Member<'a>, Member<'a>)>) -> Member<'a>,
+) -> Result<Control<'a>, RispError> {
+    let next = match args.as_slice() {
+        [seq] => seq::next(seq)?
+            .ok_or_else(|| RispError::Type(format!("{fun_name} expects a sequence, got {seq}")))?,
+        _ => return Err(RispError::Arity(format!("{fun_name} expects 1 argument"))),
+    };
+    Ok(Control::Value(match next {
+        Next::Unrealized(seq) => {
+            frames.push(Frame::Retry {
+                fun: fun_name,
+                args,
+            });
+            return realize(seq, frames, context);
+        }
+        Next::Cons(first, rest) => front(Some((first, rest))),
+        Next::Empty => front(None),
+    }))
+}
+
+/// Walks a sequence into `done`, realizing it along the way,
+/// then calls `fun_name` with the values as a list
+fn collect<'a>(
+    fun_name: Ident<'a>,
+    mut done: Vec<Member<'a>>,
+    mut rest: Member<'a>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    loop {
+        match seq::next(&rest)? {
+            Some(Next::Empty) => break,
+            Some(Next::Cons(first, next)) => {
+                done.push(first);
+                rest = next;
+            }
+            Some(Next::Unrealized(seq)) => {
+                frames.push(Frame::Collect {
+                    fun: fun_name,
+                    done,
+                    rest,
+                });
+                return realize(seq, frames, context);
+            }
+            None => {
+                return Err(RispError::Type(format!(
+                    "{fun_name} expects a sequence, got {rest}"
+                )))
+            }
+        }
+    }
+    let list = Member::List(done.into_iter().collect());
+    native(fun_name, vec![list], frames, context)
+}
+
+/// Frames running the `after` of every `dynamic-wind` or `finally` left in a suspended computation
+/// that will never be resumed, the innermost one last so that it runs first
+fn abandon<'a>(continuation: &Continuation<'a>) -> Vec<Frame<'a>> {
+    let Captured::Effect(suspended) = &continuation.0 else {
+        return vec![];
+    };
+    let Some(suspended) = suspended.borrow_mut().take() else {
+        return vec![];
+    };
+    suspended
+        .frames
+        .into_iter()
+        .filter_map(|frame| match frame {
+            Frame::Wind { after, .. } => Some(after.frame()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Looks for a handler of the condition in the frames below `top`, starting at clause
+/// `clause` of the frame right below it, and calls it without unwinding anything
+fn signal<'a>(
+    condition: Member<'a>,
+    severity: Severity,
+    top: usize,
+    mut clause: usize,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    let mut index = top;
+    while index > 0 {
+        index -= 1;
+        match &frames[index] {
+            // handlers don't see the conditions signalled by their own cluster's handlers
+            Frame::Signal { cluster, .. } => {
+                index = *cluster;
+            }
+            Frame::Handlers(handlers) => {
+                let found = handlers
+                    .iter()
+                    .enumerate()
+                    .skip(clause)
+                    .find(|(_, handler)| kind_matches(handler.kind, &condition));
+                if let Some((i, handler)) = found {
+                    let fun = handler.fun.clone();
+                    frames.push(Frame::Signal {
+                        condition: condition.clone(),
+                        severity,
+                        cluster: index,
+                        clause: i + 1,
+                    });
+                    return call_value(fun, vec![condition], frames, context);
+                }
+            }
+            _ => (),
+        }
+        clause = 0;
+    }
+    match severity {
+        Severity::Signal => Ok(Control::Value(Member::Unit)),
+        Severity::Warning => {
+            let message = match condition {
+                Member::Error(e) => e.message(),
+                other => other.into_string_value()?,
+            };
+            writeln!(context.error_output(), "Warning: {message}")
+                .map_err(|e| RispError::Runtime(format!("Could not write warning: {e}")))?;
+            Ok(Control::Value(Member::Unit))
+        }
+        Severity::Error => Ok(unhandled(condition, frames, context)),
+    }
+}
+
+/// Lets the debugger pick a restart for an error nothing handles, or throws it
+fn unhandled<'a>(
+    condition: Member<'a>,
+    frames: &mut [Frame<'a>],
+    context: &mut ProgContext<'a>,
+) -> Control<'a> {
+    let caught = frames.iter().any(|frame| match frame {
+        Frame::Catch(catches) => catches.iter().any(|c| c.catches(&condition)),
+        _ => false,
+    });
+    let restarts: Vec<(usize, &Restart<'a>)> = frames
+        .iter()
+        .enumerate()
+        .rev()
+        .flat_map(|(i, frame)| match frame {
+            Frame::Restarts(restarts) => restarts.iter().map(|r| (i, r)).collect(),
+            _ => vec![],
+        })
+        .collect();
+    if let (false, Some(debugger), Member::Error(e)) =
+        (caught, context.debugger.as_mut(), &condition)
+    {
+        let names: Vec<Ident<'a>> = restarts.iter().map(|(_, r)| r.name).collect();
+        if let Some(&(target, restart)) = debugger(e, &names).and_then(|i| restarts.get(i)) {
+            return Control::Unwind(Exit::Restart {
+                target,
+                restart: restart.clone(),
+                args: vec![],
+            });
+        }
+    }
+    Control::Unwind(Exit::Throw(condition))
+}
+
+/// Whether a `catch` or handler for errors of `kind` takes the value, `None` takes anything
+fn kind_matches<'a>(kind: Option<Ident<'a>>, value: &Member<'a>) -> bool {
+    match (kind, value) {
+        (None, _) => true,
+        (Some(kind), Member::Error(e)) => kind == e.kind,
+        _ => false,
+    }
+}
+
+fn eval<'a>(
+    member: Member<'a>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    Ok(match member {
+        Member::Ident(i) => Control::Value(match context.get_var(i) {
+            Ok(value) => value,
+            // functions are values too when no variable shadows them
+            Err(e) => context.get_fun(i).ok_or(e)?,
+        }),
+        Member::SExpr(s) => eval_sexpr(s, frames, context)?,
+        value => Control::Value(value),
+    })
+}
+
+fn eval_sexpr<'a>(
+    mut sexpr: SExpr<'a>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    // `()` is the unit value
+    let Some(first) = sexpr.members.pop_front() else {
+        return Ok(Control::Value(Member::Unit));
+    };
+    let Member::Ident(fun_name) = first else {
+        frames.push(Frame::Head(sexpr.into_members()));
+        return Ok(Control::Eval(first));
+    };
+    // special forms decide which of their arguments are evaluated, when, and where
+    // the values go. Functions get theirs evaluated in order first
+    if let Some(Member::Builtin(builtin)) = context.get_fun(fun_name) {
+        if let Native::Form(form) = &builtin.native {
+            builtin.check_arity(sexpr.members.len())?;
+            return form(fun_name, sexpr.into_members(), frames, context);
+        }
+    }
+    next_arg(
+        Callee::Name(fun_name),
+        Vec::new(),
+        sexpr.into_members(),
+        frames,
+        context,
+    )
+}
+
+fn eval_case_type<'a>(
+    _: Ident<'a>,
+    mut args: VecDeque<Member<'a>>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    let value = args
+        .pop_front()
+        .ok_or("case-type requires a value to dispatch on")?;
+    let clauses = args
+        .into_iter()
+        .map(|clause| CaseClause::parse(clause, context))
+        .collect::<Result<_, _>>()?;
+    frames.push(Frame::CaseType(clauses));
+    Ok(Control::Eval(value))
+}
+
+fn eval_let<'a>(
+    _: Ident<'a>,
+    mut args: VecDeque<Member<'a>>,
+    frames: &mut Vec<Frame<'a>>,
+    _: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    let pattern = Pattern::parse(args.pop_front().ok_or("let requires 2 arguments")?)?;
+    let value = args.pop_front().ok_or("let requires 2 arguments")?;
+    if !args.is_empty() {
+        return Err("let requires 2 arguments".into());
+    }
+    frames.push(Frame::Let(pattern));
+    Ok(Control::Eval(value))
+}
+
+fn eval_if<'a>(
+    _: Ident<'a>,
+    mut args: VecDeque<Member<'a>>,
+    frames: &mut Vec<Frame<'a>>,
+    _: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    let cond = args.pop_front().ok_or("if requires 2 or 3 arguments")?;
+    let then = args.pop_front().ok_or("if requires 2 or 3 arguments")?;
+    let otherwise = args.pop_front().unwrap_or(Member::Unit);
+    if !args.is_empty() {
+        return Err("if requires 2 or 3 arguments".into());
+    }
+    frames.push(Frame::If { then, otherwise });
+    Ok(Control::Eval(cond))
+}
+
+fn eval_do<'a>(
+    _: Ident<'a>,
+    mut args: VecDeque<Member<'a>>,
+    frames: &mut Vec<Frame<'a>>,
+    _: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    let Some(first) = args.pop_front() else {
+        return Ok(Control::Value(Member::Unit));
+    };
+    // the last form is evaluated where the `do` is, so a call there is still a tail call
+    frames.extend(args.into_iter().rev().map(Frame::Then));
+    Ok(Control::Eval(first))
+}
+
+fn eval_match<'a>(
+    _: Ident<'a>,
+    mut args: VecDeque<Member<'a>>,
+    frames: &mut Vec<Frame<'a>>,
+    _: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    let value = args.pop_front().ok_or("match requires a value to match")?;
+    let clauses = args
+        .into_iter()
+        .map(Clause::parse)
+        .collect::<Result<_, _>>()?;
+    frames.push(Frame::Match(clauses));
+    Ok(Control::Eval(value))
+}
+
+/// `lazy-seq` and `generator`, `step` makes the step that evaluates the body in the environment
+fn eval_lazy<'a>(
+    name: Ident<'a>,
+    mut args: VecDeque<Member<'a>>,
+    context: &mut ProgContext<'a>,
+    step: fn(Member<'a>, Env<'a>) -> Step<'a>,
+) -> Result<Control<'a>, RispError> {
+    let (Some(body), None) = (args.pop_front(), args.pop_front()) else {
+        return Err(format!("{name} takes a single body").into());
+    };
+    let env = context.local_env();
+    Ok(Control::Value(Member::Seq(Rc::new(Seq::new(step(
+        body, env,
+    ))))))
+}
+
+fn eval_lambda<'a>(
+    _: Ident<'a>,
+    mut args: VecDeque<Member<'a>>,
+    _: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    let (Some(Member::SExpr(params)), Some(expr), None) =
+        (args.pop_front(), args.pop_front(), args.pop_front())
+    else {
+        return Err("lambda is written (lambda (params) body)".into());
+    };
+    Ok(Control::Value(Member::Fun(Rc::new(Fun {
+        name: "lambda",
+        params: ListPattern::parse(params)?,
+        expr: Rc::new(expr),
+        captured: Rc::new(context.local_env()),
+        local: false,
+    }))))
+}
+
+fn eval_handler_bind<'a>(
+    _: Ident<'a>,
+    mut args: VecDeque<Member<'a>>,
+    frames: &mut Vec<Frame<'a>>,
+    _: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    let error = || "handler-bind is written (handler-bind ((kind handler) ...) body)";
+    let (Some(Member::SExpr(clauses)), Some(body), None) =
+        (args.pop_front(), args.pop_front(), args.pop_front())
+    else {
+        return Err(error().into());
+    };
+    let mut rest = VecDeque::new();
+    for clause in clauses.into_members() {
+        let mut members = clause.as_sexpr().ok_or_else(error)?.into_members();
+        let kind = match members.pop_front() {
+            Some(Member::Keyword(k)) => Some(k),
+            Some(Member::Ident("_")) => None,
+            _ => return Err(error().into()),
+        };
+        let (Some(fun), None) = (members.pop_front(), members.pop_front()) else {
+            return Err(error().into());
+        };
+        rest.push_back((kind, fun));
+    }
+    next_handler(Vec::new(), rest, body, frames)
+}
+
+fn eval_restart_case<'a>(
+    _: Ident<'a>,
+    mut args: VecDeque<Member<'a>>,
+    frames: &mut Vec<Frame<'a>>,
+    _: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    let body = args.pop_front().ok_or("restart-case requires a body")?;
+    let restarts = args
+        .into_iter()
+        .map(Restart::parse)
+        .collect::<Result<_, _>>()?;
+    frames.push(Frame::Restarts(restarts));
+    Ok(Control::Eval(body))
+}
+
+fn eval_handle<'a>(
+    _: Ident<'a>,
+    mut args: VecDeque<Member<'a>>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    let body = args.pop_front().ok_or("handle requires a body")?;
+    let effects = args
+        .into_iter()
+        .map(|effect| Effect::parse(effect, context))
+        .collect::<Result<_, _>>()?;
+    frames.push(Frame::Handle(effects));
+    Ok(Control::Eval(body))
+}
+
+fn eval_try<'a>(
+    _: Ident<'a>,
+    mut args: VecDeque<Member<'a>>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    let body = args.pop_front().ok_or("try requires a body")?;
+    let mut catches = Vec::new();
+    for clause in args {
+        match clause.as_sexpr().map(|s| s.into_members()) {
+            Some(mut members) if members.front() == Some(&Member::Ident("finally")) => {
+                members.pop_front();
+                let cleanup = members.pop_front().ok_or("finally requires a body")?;
+                if !members.is_empty() {
+                    return Err("finally takes a single body".into());
+                }
+                let id = context.fresh_id();
+                frames.push(Frame::Wind {
+                    id,
+                    before: None,
+                    after: Thunk::Expr(cleanup),
+                });
+            }
+            Some(members) if members.front() == Some(&Member::Ident("catch")) => {
+                catches.push(Catch::parse(members)?)
+            }
+            _ => return Err("try clauses are (catch kind var body) or (finally body)".into()),
+        }
+    }
+    frames.push(Frame::Catch(catches));
+    Ok(Control::Eval(body))
+}
+
+/// `(catch kind var body)` where `kind` is the keyword of the errors it takes,
+/// or `_` to take any thrown value. `var` may be `_` too
+#[derive(Debug, Clone)]
+pub struct Catch<'a> {
+    kind: Option<Ident<'a>>,
+    ident: Option<Ident<'a>>,
+    body: Member<'a>,
+}
+
+impl<'a> Catch<'a> {
+    fn parse(mut members: VecDeque<Member<'a>>) -> Result<Self, RispError> {
+        let error = || "catch clauses are written (catch kind var body)";
+        members.pop_front();
+        let kind = match members.pop_front().ok_or_else(error)? {
+            Member::Keyword(k) => Some(k),
+            Member::Ident("_") => None,
+            _ => return Err(error().into()),
+        };
+        let ident = match members.pop_front().and_then(Member::as_ident) {
+            Some("_") => None,
+            Some(i) => Some(i),
+            None => return Err(error().into()),
+        };
+        let body = members.pop_front().ok_or_else(error)?;
+        if !members.is_empty() {
+            return Err(error().into());
+        }
+        Ok(Self { kind, ident, body })
+    }
+
+    fn catches(&self, value: &Member<'a>) -> bool {
+        kind_matches(self.kind, value)
+    }
+}
+
+/// `(kind handler)` clause of a `handler-bind`, `kind` being a keyword or `_`
+#[derive(Debug, Clone)]
+pub struct Handler<'a> {
+    kind: Option<Ident<'a>>,
+    fun: Member<'a>,
+}
+
+/// `(:name (params) body)` clause of a `restart-case`
+#[derive(Debug, Clone)]
+pub struct Restart<'a> {
+    name: Ident<'a>,
+    params: ListPattern<'a>,
+    body: Member<'a>,
+}
+
+impl<'a> Restart<'a> {
+    fn parse(member: Member<'a>) -> Result<Self, RispError> {
+        let error = || "restart-case clauses are written (:name (params) body)";
+        let mut members = member.as_sexpr().ok_or_else(error)?.into_members();
+        let (Some(Member::Keyword(name)), Some(Member::SExpr(params)), Some(body), None) = (
+            members.pop_front(),
+            members.pop_front(),
+            members.pop_front(),
+            members.pop_front(),
+        ) else {
+            return Err(error().into());
+        };
+        Ok(Self {
+            name,
+            params: ListPattern::parse(params)?,
+            body,
+        })
+    }
+
+    /// Binds the arguments in a scope of their own and evaluates the body there
+    fn enter(
+        self,
+        args: Vec<Member<'a>>,
+        frames: &mut Vec<Frame<'a>>,
+        context: &mut ProgContext<'a>,
+    ) -> Result<Control<'a>, RispError> {
+        let mut bound = Bound::default();
+        self.params.bind(args, &mut bound).map_err(|problem| {
+            RispError::Arity(format!(
+                "Bad arguments for restart :{}: {problem}, expected {}",
+                self.name,
+                self.params.signature(self.name)
+            ))
+        })?;
+        context.open_block();
+        frames.push(Frame::Call);
+        frames.push(Frame::Then(self.body));
+        push_pending(bound.commit(context), frames);
+        Ok(Control::Value(Member::Unit))
+    }
+}
+
+/// `(:effect (params) resume body)` clause of a `handle`, called with the continuation of
+/// the `perform` bound to `resume` followed by the performed arguments
+#[derive(Debug, Clone)]
+pub struct Effect<'a> {
+    name: Ident<'a>,
+    fun: Rc<Fun<'a>>,
+}
+
+impl<'a> Effect<'a> {
+    /// Handlers see what is bound where the `handle` is, not where the effect is performed
+    fn parse(member: Member<'a>, context: &ProgContext<'a>) -> Result<Self, RispError> {
+        let error = || "handle clauses are written (:effect (params) resume body)";
+        let mut members = member.as_sexpr().ok_or_else(error)?.into_members();
+        let (
+            Some(Member::Keyword(name)),
+            Some(Member::SExpr(mut params)),
+            Some(Member::Ident(resume)),
+            Some(body),
+            None,
+        ) = (
+            members.pop_front(),
+            members.pop_front(),
+            members.pop_front(),
+            members.pop_front(),
+            members.pop_front(),
+        )
+        else {
+            return Err(error().into());
+        };
+        params.members.push_front(Member::Ident(resume));
+        Ok(Self {
+            name,
+            fun: Rc::new(Fun {
+                name,
+                params: ListPattern::parse(params)?,
+                expr: Rc::new(body),
+                captured: Rc::new(context.local_env()),
+                local: false,
+            }),
+        })
+    }
+}
+
+/// `(pattern body)` or `(pattern when guard body)`
+#[derive(Debug, Clone)]
+pub struct Clause<'a> {
+    pattern: Pattern<'a>,
+    guard: Option<Member<'a>>,
+    body: Member<'a>,
+}
+
+impl<'a> Clause<'a> {
+    fn parse(member: Member<'a>) -> Result<Self, RispError> {
+        let error = || "match clauses are written (pattern body) or (pattern when guard body)";
+        let mut members = member.as_sexpr().ok_or_else(error)?.into_members();
+        let pattern = Pattern::parse(members.pop_front().ok_or_else(error)?)?;
+        let guard = match members.front() {
+            Some(Member::Ident("when")) => {
+                members.pop_front();
+                Some(members.pop_front().ok_or_else(error)?)
+            }
+            _ => None,
+        };
+        let body = members.pop_front().ok_or_else(error)?;
+        if !members.is_empty() {
+            return Err(error().into());
+        }
+        Ok(Self {
+            pattern,
+            guard,
+            body,
+        })
+    }
+}
+
+/// `(variant (field...) body)`, or `(_ body)` for the variants no other clause covers
+#[derive(Debug, Clone)]
+pub struct CaseClause<'a> {
+    variant: Option<Ident<'a>>,
+    fields: ListPattern<'a>,
+    body: Member<'a>,
+}
+
+impl<'a> CaseClause<'a> {
+    fn parse(member: Member<'a>, context: &ProgContext<'a>) -> Result<Self, RispError> {
+        let error = || "case-type clauses are written (variant (field...) body) or (_ body)";
+        let mut members = member.as_sexpr().ok_or_else(error)?.into_members();
+        let variant = members
+            .pop_front()
+            .and_then(Member::as_ident)
+            .ok_or_else(error)?;
+        let (variant, fields) = match variant {
+            "_" => (None, ListPattern::default()),
+            variant => {
+                if context.types.get(variant).is_none_or(|ty| ty.sum.is_none()) {
+                    return Err(RispError::Unbound(format!(
+                        "{variant} is not a variant of any deftype"
+                    )));
+                }
+                let fields = members
+                    .pop_front()
+                    .and_then(Member::as_sexpr)
+                    .ok_or_else(error)?;
+                (Some(variant), ListPattern::parse(fields)?)
+            }
+        };
+        let (Some(body), None) = (members.pop_front(), members.pop_front()) else {
+            return Err(error().into());
+        };
+        Ok(Self {
+            variant,
+            fields,
+            body,
+        })
+    }
+}
+
+/// Evaluates the body of the clause covering the variant of `value` with its fields bound
+/// in a scope of their own, fails with the variants left uncovered if there is none
+fn case_type<'a>(
+    value: Member<'a>,
+    clauses: Vec<CaseClause<'a>>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    let (Member::Struct(s), Some(sum)) = (&value, value.variant_of()) else {
+        return Err(RispError::Type(format!(
+            "case-type expects a value of a deftype, got {value}"
+        )));
+    };
+    let Some(clause) = clauses
+        .iter()
+        .find(|clause| clause.variant.is_none_or(|variant| variant == s.ty.name))
+    else {
+        let uncovered: Vec<Ident> = context.sums[sum]
+            .iter()
+            .copied()
+            .filter(|variant| !clauses.iter().any(|clause| clause.variant == Some(variant)))
+            .collect();
+        return Err(format!(
+            "case-type on {sum} got a {} but doesn't cover {}",
+            s.ty.name,
+            uncovered.join(", ")
+        )
+        .into());
+    };
+    let mut bound = Bound::default();
+    if clause.variant.is_some() {
+        clause
+            .fields
+            .bind(s.values.iter().cloned(), &mut bound)
+            .map_err(|problem| format!("{problem} for the fields of {}", s.ty.name))?;
+    }
+    context.open_block();
+    frames.push(Frame::Call);
+    frames.push(Frame::Then(clause.body.clone()));
+    push_pending(bound.commit(context), frames);
+    Ok(Control::Value(Member::Unit))
+}
+
+/// Evaluates the next handler of a `handler-bind`, or its body once they all have a value
+#[allow(clippy::type_complexity)]
+fn next_handler<'a>(
+    done: Vec<Handler<'a>>,
+    mut rest: VecDeque<(Option<Ident<'a>>, Member<'a>)>,
+    body: Member<'a>,
+    frames: &mut Vec<Frame<'a>>,
+) -> Result<Control<'a>, RispError> {
+    match rest.pop_front() {
+        Some((kind, fun)) => {
+            frames.push(Frame::HandlerBind {
+                done,
+                kind,
+                rest,
+                body,
+            });
+            Ok(Control::Eval(fun))
+        }
+        None => {
+            frames.push(Frame::Handlers(done));
+            Ok(Control::Eval(body))
+        }
+    }
+}
+
+/// Binds the first clause whose pattern matches `value` in a scope of its own
+/// and evaluates its guard or body there
+fn match_clauses<'a>(
+    value: Member<'a>,
+    mut clauses: VecDeque<Clause<'a>>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    while let Some(clause) = clauses.pop_front() {
+        let mut bound = Bound::default();
+        if clause.pattern.bind(value.clone(), &mut bound).is_err() {
+            continue;
+        }
+        context.open_block();
+        frames.push(Frame::Call);
+        match clause.guard {
+            Some(guard) => {
+                frames.push(Frame::Guard {
+                    value,
+                    body: clause.body,
+                    clauses,
+                });
+                frames.push(Frame::Then(guard));
+            }
+            None => frames.push(Frame::Then(clause.body)),
+        }
+        push_pending(bound.commit(context), frames);
+        return Ok(Control::Value(Member::Unit));
+    }
+    Err(format!("No match clause matched {value}").into())
+}
+
+fn next_arg<'a>(
+    fun: Callee<'a>,
+    done: Vec<Member<'a>>,
+    mut rest: VecDeque<Member<'a>>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    match rest.pop_front() {
+        Some(arg) => {
+            frames.push(Frame::Args { fun, done, rest });
+            Ok(Control::Eval(arg))
+        }
+        None => apply(fun, done, frames, context),
+    }
+}
+
+fn apply<'a>(
+    fun: Callee<'a>,
+    args: Vec<Member<'a>>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    let depth = context.depth();
+    context.trace(Event::Call {
+        fun: fun.name(),
+        args: &args,
+        depth,
+    });
+    let fun = match fun {
+        Callee::Value(fun) => fun,
+        Callee::Name(name) => match context.get_fun(name) {
+            Some(fun) => fun,
+            // variables holding a function can be called like one
+            None => context
+                .get_var(name)
+                .map_err(|_| RispError::Unbound(format!("Function {name} not found")))?,
+        },
+    };
+    call_value(fun, args, frames, context)
+}
+
+/// Calls the builtin registered as `name` whatever the program bound to the name,
+/// for builtins carrying on once a sequence is realized
+fn native<'a>(
+    name: Ident<'a>,
+    args: Vec<Member<'a>>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    context.builtin(name)?.call(args, frames, context)
+}
+
+/// Builtins every context starts with, those of the other modules included
+fn registry<'a>() -> Vec<Builtin<'a>> {
+    use Arity::{AtLeast, Between, Exactly};
+    let mut builtins =
+        vec![
+        // special forms
+        Builtin::form(
+            "def",
+            Exactly(3),
+            "(def name (params) body) defines a function in the current scope",
+            |_, args, _, context| Ok(Control::Value(define_fun(args, context)?)),
+        ),
+        Builtin::form(
+            "defstruct",
+            AtLeast(1),
+            "(defstruct name field...) defines make-name, name? and an accessor per field",
+            |_, args, _, context| Ok(Control::Value(define_struct(args, context)?)),
+        ),
+        Builtin::form(
+            "deftype",
+            AtLeast(1),
+            "(deftype name (variant field...)...) defines a sum type, its variants and name?",
+            |_, args, _, context| Ok(Control::Value(define_type(args, context)?)),
+        ),
+        Builtin::form(
+            "case-type",
+            AtLeast(1),
+            "(case-type value ((variant field...) body)...) evaluates the clause of the variant",
+            eval_case_type,
+        ),
+        Builtin::form(
+            "let",
+            Exactly(2),
+            "(let pattern value) binds the pattern in the current scope",
+            eval_let,
+        ),
+        Builtin::form(
+            "if",
+            Between(2, 3),
+            "(if cond then [else]) evaluates then unless cond is false or ()",
+            eval_if,
+        ),
+        Builtin::form(
+            "do",
+            AtLeast(0),
+            "(do forms...) evaluates the forms in order, the value of the last one",
+            eval_do,
+        ),
+        Builtin::form(
+            "match",
+            AtLeast(1),
+            "(match value (pattern [when guard] body)...) evaluates the first clause matching",
+            eval_match,
+        ),
+        Builtin::form(
+            "lazy-seq",
+            Exactly(1),
+            "(lazy-seq body) sequence evaluated from body once it's first needed",
+            |name, args, _, context| {
+                eval_lazy(name, args, context, |body, env| Step::Expr { body, env })
+            },
+        ),
+        Builtin::form(
+            "generator",
+            Exactly(1),
+            "(generator body) sequence of the values body yields",
+            |name, args, _, context| {
+                eval_lazy(name, args, context, |body, env| Step::Generator { body, env })
+            },
+        ),
+        Builtin::form(
+            "lambda",
+            Exactly(2),
+            "(lambda (params) body) anonymous function",
+            eval_lambda,
+        ),
+        Builtin::form(
+            "handler-bind",
+            Exactly(2),
+            "(handler-bind ((kind handler)...) body) calls the handler of the conditions signalled",
+            eval_handler_bind,
+        ),
+        Builtin::form(
+            "restart-case",
+            AtLeast(1),
+            "(restart-case body (:name (params) body)...) restarts invoke-restart can pick",
+            eval_restart_case,
+        ),
+        Builtin::form(
+            "handle",
+            AtLeast(1),
+            "(handle body (:effect (params) resume body)...) handles the effects performed",
+            eval_handle,
+        ),
+        Builtin::form(
+            "try",
+            AtLeast(1),
+            "(try body (catch kind var body)... (finally body)) catches thrown values",
+            eval_try,
+        ),
+        // control
+        Builtin::control(
+            "throw",
+            Between(1, 2),
+            "(throw value) or (throw :kind message) unwinds to the innermost catch taking it",
+            |_, args, _, context| fun_std_throw(args, context),
+        ),
+        Builtin::control(
+            "raise",
+            Between(1, 2),
+            "(raise value) or (raise :kind message), same as throw",
+            |_, args, _, context| fun_std_throw(args, context),
+        ),
+        Builtin::control(
+            "signal",
+            Between(1, 2),
+            "(signal [:kind] message) hands a condition to its handler, returns () without one",
+            |_, args, frames, context| {
+                fun_std_signal(args, Severity::Signal, "condition", frames, context)
+            },
+        ),
+        Builtin::control(
+            "warn",
+            Between(1, 2),
+            "(warn [:kind] message) signals a warning, printed if no handler takes it",
+            |_, args, frames, context| {
+                fun_std_signal(args, Severity::Warning, "warning", frames, context)
+            },
+        ),
+        Builtin::control(
+            "error",
+            Between(1, 2),
+            "(error [:kind] message) signals an error, thrown if no handler takes it",
+            |_, args, frames, context| {
+                fun_std_signal(args, Severity::Error, "error", frames, context)
+            },
+        ),
+        Builtin::control(
+            "invoke-restart",
+            AtLeast(1),
+            "(invoke-restart :name args...) runs the restart of the innermost restart-case with it",
+            |_, args, frames, _| fun_std_invoke_restart(args, frames),
+        ),
+        Builtin::control(
+            "call/cc",
+            Exactly(1),
+            "(call/cc f) calls f with the continuation of the call",
+            call_cc,
+        ),
+        Builtin::control(
+            "call-with-current-continuation",
+            Exactly(1),
+            "(call-with-current-continuation f), same as call/cc",
+            call_cc,
+        ),
+        Builtin::control(
+            "call/ec",
+            Exactly(1),
+            "(call/ec f) calls f with an escape out of the call",
+            call_ec,
+        ),
+        Builtin::control(
+            "call-with-escape-continuation",
+            Exactly(1),
+            "(call-with-escape-continuation f), same as call/ec",
+            call_ec,
+        ),
+        Builtin::control(
+            "dynamic-wind",
+            Exactly(3),
+            "(dynamic-wind before thunk after) calls after whenever control leaves thunk",
+            |_, args, frames, context| {
+                let [before, thunk, after] = <[Member; 3]>::try_from(args).unwrap();
+                frames.push(Frame::Winding {
+                    before: Thunk::Call(before.clone()),
+                    thunk,
+                    after: Thunk::Call(after),
+                });
+                call_value(before, vec![], frames, context)
+            },
+        ),
+        Builtin::control(
+            "perform",
+            AtLeast(1),
+            "(perform :effect args...) calls the clause of the innermost handle for the effect",
+            |_, args, frames, context| perform(args, frames, context),
+        ),
+        Builtin::control(
+            "funcall",
+            AtLeast(1),
+            "(funcall f args...) calls the function f evaluates to",
+            |_, args, frames, context| {
+                let mut args = args.into_iter();
+                let fun = args.next().unwrap();
+                call_value(fun, args.collect(), frames, context)
+            },
+        ),
+        Builtin::control(
+            "apply",
+            AtLeast(2),
+            "(apply f args... list) calls f with the args followed by the values of the list",
+            |_, mut args, frames, context| {
+                let spread = args.pop().unwrap();
+                let fun = args.remove(0);
+                match spread {
+                    Member::List(l) => args.extend(l.iter().cloned()),
+                    Member::Vector(v) => args.extend(v.iter().cloned()),
+                    other => {
+                        return Err(RispError::Type(format!(
+                            "apply expects a list or a vector last, got {other}"
+                        )))
+                    }
+                }
+                call_value(fun, args, frames, context)
+            },
+        ),
+        Builtin::control(
+            "yield",
+            Exactly(1),
+            "(yield value) hands a value to the sequence of the innermost generator",
+            |_, args, frames, context| fun_std_yield(args, frames, context),
+        ),
+        // numbers
+        Builtin::value(
+            "+",
+            AtLeast(1),
+            "(+ a b...) sum of numbers, or strings joined",
+            |_, args, _| fun_std_sum(args),
+        ),
+        Builtin::value(
+            "-",
+            AtLeast(1),
+            "(- a b...) a minus the others, or -a alone",
+            |_, args, _| fun_std_sub(args),
+        ),
+        Builtin::value(
+            "*",
+            AtLeast(0),
+            "(* a b...) product of numbers",
+            |_, args, _| fun_std_mul(args),
+        ),
+        Builtin::value(
+            "=",
+            AtLeast(1),
+            "(= a b...) whether the values are all equal",
+            |_, args, _| fun_std_eq(args),
+        ),
+        Builtin::value(
+            "<",
+            AtLeast(1),
+            "(< a b...) whether the numbers are increasing",
+            |_, args, _| fun_std_lt(args),
+        ),
+        // errors
+        Builtin::value(
+            "error?",
+            Exactly(1),
+            "(error? value) whether the value is an error",
+            |_, args, _| Ok(Member::Bool(matches!(args.as_slice(), [Member::Error(_)]))),
+        ),
+        Builtin::value(
+            "error-kind",
+            Exactly(1),
+            "(error-kind e) keyword of the kind of an error",
+            |name, args, _| Ok(Member::Keyword(expect_error(name, args)?.kind)),
+        ),
+        Builtin::value(
+            "error-message",
+            Exactly(1),
+            "(error-message e) message of an error",
+            |name, args, _| Ok(Member::String(expect_error(name, args)?.message())),
+        ),
+        Builtin::value(
+            "error-stack",
+            Exactly(1),
+            "(error-stack e) functions being called when an error was raised, innermost first",
+            |name, args, _| {
+                Ok(Member::List(
+                    expect_error(name, args)?
+                        .stack
+                        .iter()
+                        .map(|f| Member::String(f.clone()))
+                        .collect(),
+                ))
+            },
+        ),
+        // lists and sequences
+        Builtin::value(
+            "list",
+            AtLeast(0),
+            "(list values...) list of the values",
+            |_, args, _| Ok(Member::List(args.into_iter().collect())),
+        ),
+        Builtin::value(
+            "cons",
+            Exactly(2),
+            "(cons value list) list starting with value followed by list",
+            |_, args, _| fun_std_cons(args),
+        ),
+        Builtin::control(
+            "first",
+            Exactly(1),
+            "(first seq) first value of a sequence, () if it's empty",
+            |name, args, frames, context| {
+                fun_std_front(name, args, frames, context, |front| front.map_or(Member::Unit, |(first, _)| first))
+            },
+        ),
+        Builtin::control(
+            "rest",
+            Exactly(1),
+            "(rest seq) sequence after the first value",
+            |name, args, frames, context| {
+                fun_std_front(name, args, frames, context, |front| front.map_or(Member::List(List::new()), |(_, rest)| rest))
+            },
+        ),
+        Builtin::control(
+            "empty?",
+            Exactly(1),
+            "(empty? seq) whether a sequence has no values",
+            |name, args, frames, context| {
+                fun_std_front(name, args, frames, context, |front| Member::Bool(front.is_none()))
+            },
+        ),
+        Builtin::control(
+            "length",
+            Exactly(1),
+            "(length coll) number of values of a sequence or collection, of chars of a string",
+            |name, args, frames, context| {
+                Ok(Control::Value(Member::NumI(match &args[0] {
+                    Member::List(l) => l.len(),
+                    Member::String(s) => s.chars().count(),
+                    Member::Vector(v) => v.len(),
+                    Member::Map(m) => m.len(),
+                    Member::Set(s) => s.len(),
+                    _ => return collect(name, vec![], args[0].clone(), frames, context),
+                } as i32)))
+            },
+        ),
+        Builtin::control(
+            "to-list",
+            Exactly(1),
+            "(to-list seq) list of the values of a sequence or vector, realizing it",
+            |name, mut args, frames, context| match args.pop().unwrap() {
+                list @ Member::List(_) => Ok(Control::Value(list)),
+                vector @ Member::Vector(_) => native("vector->list", vec![vector], frames, context),
+                seq => collect(name, vec![], seq, frames, context),
+            },
+        ),
+        Builtin::value(
+            "range",
+            Between(0, 3),
+            "(range [start] [end] [step]) lazy sequence of numbers, endless without an end",
+            |_, args, _| fun_std_range(args),
+        ),
+        Builtin::value(
+            "iterate",
+            Exactly(2),
+            "(iterate f value) lazy sequence of value, (f value), (f (f value))...",
+            |_, args, _| {
+                let [fun, value] = <[Member; 2]>::try_from(args).unwrap();
+                let rest = Seq::new(Step::Iterate {
+                    fun,
+                    value: value.clone(),
+                });
+                Ok(Member::Seq(Rc::new(Seq::cons(
+                    value,
+                    Member::Seq(Rc::new(rest)),
+                ))))
+            },
+        ),
+        Builtin::value(
+            "take",
+            Exactly(2),
+            "(take n seq) lazy sequence of the first n values",
+            |name, args, _| take_or_drop(name, args, |n, seq| Step::Take { n, seq }),
+        ),
+        Builtin::value(
+            "drop",
+            Exactly(2),
+            "(drop n seq) lazy sequence of the values after the first n",
+            |name, args, _| take_or_drop(name, args, |n, seq| Step::Drop { n, seq }),
+        ),
+        Builtin::value(
+            "take-while",
+            Exactly(2),
+            "(take-while pred seq) lazy sequence of the values up to the first failing pred",
+            |_, args, _| {
+                let [pred, seq] = <[Member; 2]>::try_from(args).unwrap();
+                Ok(Member::Seq(Rc::new(Seq::new(Step::TakeWhile { pred, seq }))))
+            },
+        ),
+        // vectors
+        Builtin::value(
+            "vector",
+            AtLeast(0),
+            "(vector values...) vector of the values",
+            |_, args, _| Ok(Member::Vector(args.into_iter().collect())),
+        ),
+        Builtin::value(
+            "vector->list",
+            Exactly(1),
+            "(vector->list v) list of the values of a vector",
+            |name, mut args, _| {
+                Ok(Member::List(
+                    expect_vector(name, args.pop())?.iter().cloned().collect(),
+                ))
+            },
+        ),
+        Builtin::value(
+            "list->vector",
+            Exactly(1),
+            "(list->vector l) vector of the values of a list",
+            |name, args, _| {
+                Ok(Member::Vector(
+                    expect_list(name, args)?.iter().cloned().collect(),
+                ))
+            },
+        ),
+        Builtin::value(
+            "vector-ref",
+            Exactly(2),
+            "(vector-ref v index) value at the index",
+            |name, args, _| {
+                let (vector, index) = expect_index(name, &args)?;
+                Ok(vector.get(index).unwrap().clone())
+            },
+        ),
+        Builtin::value(
+            "vector-set",
+            Exactly(3),
+            "(vector-set v index value) copy of v with the value at the index replaced, v is unchanged",
+            |name, args, _| {
+                let (vector, index) = expect_index(name, &args)?;
+                let [_, _, value] = <[Member; 3]>::try_from(args).unwrap();
+                Ok(Member::Vector(vector.set(index, value).unwrap()))
+            },
+        ),
+        Builtin::value(
+            "push",
+            AtLeast(1),
+            "(push v values...) vector with the values added at the end",
+            |name, args, _| {
+                let mut args = args.into_iter();
+                let vector = expect_vector(name, args.next())?;
+                Ok(Member::Vector(
+                    args.fold(vector, |vector, value| vector.push(value)),
+                ))
+            },
+        ),
+        Builtin::value(
+            "conj",
+            AtLeast(1),
+            "(conj coll values...) collection with the values added where it's cheapest",
+            |_, args, _| fun_std_conj(args),
+        ),
+        Builtin::value(
+            "slice",
+            Between(2, 3),
+            "(slice v start [end]) vector of the values from start up to end",
+            |_, args, _| fun_std_slice(args),
+        ),
+        // maps, sets and structs
+        Builtin::value(
+            "hash-map",
+            AtLeast(0),
+            "(hash-map key value...) map of the entries",
+            |_, args, _| fun_std_assoc(Member::Map(Map::new()), args),
+        ),
+        Builtin::value(
+            "hash-set",
+            AtLeast(0),
+            "(hash-set values...) set of the values",
+            |_, args, _| Ok(Member::Set(args.into_iter().collect())),
+        ),
+        Builtin::value(
+            "get",
+            Between(2, 3),
+            "(get coll key [default]) value of a key of a map, set or struct",
+            |_, args, _| fun_std_get(args),
+        ),
+        Builtin::value(
+            "contains?",
+            Exactly(2),
+            "(contains? coll key) whether a map or set has the key",
+            |_, args, _| fun_std_contains(args),
+        ),
+        Builtin::value(
+            "assoc",
+            AtLeast(1),
+            "(assoc coll key value...) map or struct with the entries set",
+            |_, args, _| {
+                let mut args = args.into_iter();
+                let map = args.next().unwrap();
+                fun_std_assoc(map, args.collect())
+            },
+        ),
+        Builtin::value(
+            "dissoc",
+            AtLeast(1),
+            "(dissoc map keys...) map without the keys",
+            |name, args, _| {
+                let mut args = args.into_iter();
+                let map = expect_map(name, args.next())?;
+                Ok(Member::Map(args.fold(map, |map, key| map.remove(&key))))
+            },
+        ),
+        Builtin::value(
+            "keys",
+            Exactly(1),
+            "(keys map) list of the keys of a map",
+            |name, args, _| {
+                Ok(Member::List(
+                    expect_map(name, args.into_iter().next())?
+                        .keys()
+                        .cloned()
+                        .collect(),
+                ))
+            },
+        ),
+        Builtin::value(
+            "vals",
+            Exactly(1),
+            "(vals map) list of the values of a map",
+            |name, args, _| {
+                Ok(Member::List(
+                    expect_map(name, args.into_iter().next())?
+                        .values()
+                        .cloned()
+                        .collect(),
+                ))
+            },
+        ),
+        Builtin::value(
+            "merge",
+            AtLeast(0),
+            "(merge maps...) map of the entries of all the maps, the last ones winning",
+            |name, args, _| {
+                let mut merged = Map::new();
+                for map in args {
+                    for (key, value) in expect_map(name, Some(map))?.iter() {
+                        merged = merged.insert(key.clone(), value.clone());
+                    }
+                }
+                Ok(Member::Map(merged))
+            },
+        ),
+        Builtin::value(
+            "union",
+            AtLeast(1),
+            "(union sets...) set of the values in any of the sets",
+            |name, args, _| {
+                fun_std_set_op(name, args, |result, set| {
+                    set.iter()
+                        .fold(result, |result, value| result.insert(value.clone()))
+                })
+            },
+        ),
+        Builtin::value(
+            "intersection",
+            AtLeast(1),
+            "(intersection sets...) set of the values in all of the sets",
+            |name, args, _| {
+                fun_std_set_op(name, args, |result, set| {
+                    result
+                        .iter()
+                        .filter(|value| !set.contains(value))
+                        .fold(result.clone(), |result, value| result.remove(value))
+                })
+            },
+        ),
+        // called by the functions `defstruct` and `deftype` generate
+        Builtin::value(
+            "%make-struct",
+            AtLeast(1),
+            "(%make-struct type values...) struct of the type",
+            |_, args, context| {
+                let mut args = args.into_iter();
+                let ty = struct_type(args.next(), context)?;
+                Ok(Member::Struct(Rc::new(Struct {
+                    ty,
+                    values: args.collect(),
+                })))
+            },
+        ),
+        Builtin::value(
+            "%sum?",
+            Exactly(2),
+            "(%sum? :type value) whether the value is a variant of the sum type",
+            |_, args, _| match (&args[0], &args[1]) {
+                (Member::Keyword(sum), value) => Ok(Member::Bool(value.variant_of() == Some(sum))),
+                (other, _) => Err(RispError::Type(format!(
+                    "Sum types are named by a keyword, got {other}"
+                ))),
+            },
+        ),
+        Builtin::value(
+            "%struct?",
+            Exactly(2),
+            "(%struct? type value) whether the value is a struct of the type",
+            |_, args, context| {
+                let ty = struct_type(args.first().cloned(), context)?;
+                Ok(Member::Bool(
+                    matches!(args.get(1), Some(Member::Struct(s)) if Rc::ptr_eq(&s.ty, &ty)),
+                ))
+            },
+        ),
+        Builtin::value(
+            "%struct-get",
+            Exactly(3),
+            "(%struct-get type value :field) field of a struct of the type",
+            |_, args, context| {
+                let [ty, value, field] = <[Member; 3]>::try_from(args).unwrap();
+                let ty = struct_type(Some(ty), context)?;
+                match (value, field) {
+                    (Member::Struct(s), Member::Keyword(field)) if Rc::ptr_eq(&s.ty, &ty) => {
+                        // an accessor of a previous definition of the type may ask for
+                        // a field it no longer has
+                        s.get(field).cloned().ok_or_else(|| {
+                            RispError::Type(format!("{} has no field :{field}", ty.name))
+                        })
+                    }
+                    (Member::Struct(s), field) if Rc::ptr_eq(&s.ty, &ty) => Err(RispError::Type(
+                        format!("Fields are named by a keyword, got {field}"),
+                    )),
+                    (other, _) => Err(RispError::Type(format!(
+                        "Expected a {}, got {other}",
+                        ty.name
+                    ))),
+                }
+            },
+        ),
+        // text
+        Builtin::value(
+            "format",
+            AtLeast(1),
+            "(format template values... :name value...) template with its directives filled",
+            |_, args, context| format::format(args, context),
+        ),
+        Builtin::value(
+            "pretty",
+            Between(1, 2),
+            "(pretty value [width]) value written over as many lines as fit in the width",
+            |_, args, _| printer::fun_std_pretty(args),
+        ),
+        // help
+        Builtin::value(
+            "builtins",
+            Exactly(0),
+            "(builtins) names of the builtins, in order",
+            |_, _, context| {
+                Ok(Member::List(
+                    context
+                        .builtins()
+                        .iter()
+                        .filter(|builtin| !builtin.name.starts_with('%'))
+                        .map(|builtin| Member::String(builtin.name.to_string()))
+                        .collect(),
+                ))
+            },
+        ),
+        Builtin::value(
+            "doc",
+            Exactly(1),
+            "(doc f) how a function or builtin is called, and what a builtin does",
+            |_, args, _| match &args[0] {
+                Member::Builtin(builtin) => Ok(Member::String(builtin.doc.to_string())),
+                Member::Fun(fun) => Ok(Member::String(fun.params.signature(fun.name))),
+                other => Err(RispError::Type(format!(
+                    "doc expects a function, got {other}"
+                ))),
+            },
+        ),
+    ];
+    builtins.extend(strings::builtins());
+    builtins.extend(console::builtins());
+    builtins.extend(files::builtins());
+    builtins.extend(process::builtins());
+    builtins
+}
+
+fn call_cc<'a>(
+    _: Ident<'a>,
+    args: Vec<Member<'a>>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    let [fun] = <[Member; 1]>::try_from(args).unwrap();
+    let continuation = Continuation(Captured::Full {
+        frames: frames.clone(),
+        scopes: context.snapshot(),
+    });
+    let args = vec![Member::Continuation(Rc::new(continuation))];
+    call_value(fun, args, frames, context)
+}
+
+fn call_ec<'a>(
+    _: Ident<'a>,
+    args: Vec<Member<'a>>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    let [fun] = <[Member; 1]>::try_from(args).unwrap();
+    let id = context.fresh_id();
+    frames.push(Frame::Escape(id));
+    let args = vec![Member::Continuation(Rc::new(Continuation(
+        Captured::Escape(id),
+    )))];
+    call_value(fun, args, frames, context)
+}
+
+/// `(take n seq)` or `(drop n seq)`, `step` makes the lazy step of the count and sequence
+fn take_or_drop<'a>(
+    name: &str,
+    args: Vec<Member<'a>>,
+    step: fn(usize, Member<'a>) -> Step<'a>,
+) -> OpResult<'a> {
+    let [n, seq] = <[Member; 2]>::try_from(args).unwrap();
+    let n = n.into_inum_value()?;
+    let n = usize::try_from(n)
+        .map_err(|_| RispError::Type(format!("{name} expects a count, got {n}")))?;
+    Ok(Member::Seq(Rc::new(Seq::new(step(n, seq)))))
+}
+
+fn call_value<'a>(
+    fun: Member<'a>,
+    args: Vec<Member<'a>>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    let fun = match fun {
+        Member::Fun(fun) => fun,
+        Member::Builtin(builtin) => return builtin.call(args, frames, context),
+        Member::Continuation(continuation) => {
+            let value = match <[Member; 1]>::try_from(args) {
+                Ok([value]) => value,
+                Err(args) if args.is_empty() => Member::Unit,
+                Err(_) => return Err(RispError::Arity("Continuations take a single value".into())),
+            };
+            return resume_continuation(continuation, value, frames, context);
+        }
+        fun => return Err(RispError::Type(format!("{fun} is not a function"))),
+    };
+    // a call in tail position takes over the caller's frame instead of stacking on it,
+    // along with the frames of the blocks it's the last thing of
+    while let Some(Frame::Call) = frames.last() {
+        frames.pop();
+        context.pop_level();
+    }
+    let (pending, body) = fun.call(args, context)?;
+    frames.push(Frame::Call);
+    frames.push(Frame::Then(body));
+    push_pending(pending, frames);
+    Ok(Control::Value(Member::Unit))
+}
+
+/// Schedules the evaluation of each default and its binding, in order.
+/// The value handed to the frames pushed here is discarded
+fn push_pending<'a>(pending: Pending<'a>, frames: &mut Vec<Frame<'a>>) {
+    for (pattern, default) in pending.into_iter().rev() {
+        frames.push(Frame::Let(pattern));
+        frames.push(Frame::Then(default));
+    }
+}
+
+fn resume<'a>(
+    frame: Frame<'a>,
+    value: Member<'a>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    match frame {
+        Frame::Args {
+            fun,
+            mut done,
+            rest,
+        } => {
+            done.push(value);
+            next_arg(fun, done, rest, frames, context)
+        }
+        Frame::Head(rest) => match value {
+            fun @ (Member::Fun(_) | Member::Builtin(_) | Member::Continuation(_)) => {
+                next_arg(Callee::Value(fun), Vec::new(), rest, frames, context)
+            }
+            value => Err(RispError::Type(format!("{value} is not callable"))),
+        },
+        Frame::Let(pattern) => {
+            let mut bound = Bound::default();
+            pattern.bind(value.clone(), &mut bound).map_err(|problem| {
+                RispError::Type(format!("Cannot destructure {value}: {problem}"))
+            })?;
+            frames.push(Frame::Const(value));
+            push_pending(bound.commit(context), frames);
+            Ok(Control::Value(Member::Unit))
+        }
+        Frame::Match(clauses) => match_clauses(value, clauses, frames, context),
+        Frame::CaseType(clauses) => case_type(value, clauses, frames, context),
+        Frame::Guard {
+            value: matched,
+            body,
+            clauses,
+        } => {
+            if value.is_truthy() {
+                Ok(Control::Eval(body))
+            } else {
+                // the scope of the clause is left before trying the next ones
+                frames.pop();
+                context.pop_level();
+                match_clauses(matched, clauses, frames, context)
+            }
+        }
+        Frame::Catch(_) => Ok(Control::Value(value)),
+        Frame::Wind { after, .. } => {
+            frames.push(Frame::Const(value));
+            frames.push(after.frame());
+            Ok(Control::Value(Member::Unit))
+        }
+        Frame::Winding {
+            before,
+            thunk,
+            after,
+        } => {
+            let id = context.fresh_id();
+            frames.push(Frame::Wind {
+                id,
+                before: Some(before),
+                after,
+            });
+            call_value(thunk, vec![], frames, context)
+        }
+        Frame::Escape(_) => Ok(Control::Value(value)),
+        Frame::Invoke(fun) => call_value(fun, vec![], frames, context),
+        Frame::Handle(_) => Ok(Control::Value(value)),
+        Frame::Realize(seq) => match seq.fill(&value)? {
+            None => Ok(Control::Value(Member::Unit)),
+            // the value is a sequence still to be realized itself, it's filled in once it is
+            Some(inner) => {
+                frames.push(Frame::Realize(seq));
+                frames.push(Frame::Const(value));
+                realize(inner, frames, context)
+            }
+        },
+        Frame::Generate => Ok(Control::Value(Member::Unit)),
+        Frame::Iterated(fun) => {
+            let rest = Seq::new(Step::Iterate {
+                fun,
+                value: value.clone(),
+            });
+            Ok(Control::Value(Member::Seq(Rc::new(Seq::cons(
+                value,
+                Member::Seq(Rc::new(rest)),
+            )))))
+        }
+        Frame::TakeWhile { pred, first, rest } => Ok(Control::Value(if value.is_truthy() {
+            let rest = Seq::new(Step::TakeWhile { pred, seq: rest });
+            Member::Seq(Rc::new(Seq::cons(first, Member::Seq(Rc::new(rest)))))
+        } else {
+            Member::Unit
+        })),
+        Frame::Retry { fun, args } => native(fun, args, frames, context),
+        Frame::Collect { fun, done, rest } => collect(fun, done, rest, frames, context),
+        Frame::Handling(continuation) => {
+            frames.push(Frame::Const(value));
+            frames.extend(abandon(&continuation));
+            Ok(Control::Value(Member::Unit))
+        }
+        Frame::Resume(exit) => Ok(Control::Unwind(exit)),
+        Frame::HandlerBind {
+            mut done,
+            kind,
+            rest,
+            body,
+        } => {
+            done.push(Handler { kind, fun: value });
+            next_handler(done, rest, body, frames)
+        }
+        Frame::Handlers(_) | Frame::Restarts(_) => Ok(Control::Value(value)),
+        Frame::Signal {
+            condition,
+            severity,
+            cluster,
+            clause,
+        } => signal(condition, severity, cluster + 1, clause, frames, context),
+        Frame::Then(member) => Ok(Control::Eval(member)),
+        Frame::Const(value) => Ok(Control::Value(value)),
+        Frame::If { then, otherwise } => Ok(Control::Eval(if value.is_truthy() {
+            then
+        } else {
+            otherwise
+        })),
+        Frame::Call => {
+            context.pop_level();
+            Ok(Control::Value(value))
+        }
+    }
+}
+
+fn fun_std_sum<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    let mut args = args.into_iter();
+    let mut res = args.next().ok_or("Sum called with no arguments")?;
+    for rhs in args {
+        res = match res {
+            Member::Ident(i) => {
+                return Err(RispError::Type(format!(
+                    "Attempted to add to the unresolved name {i}"
+                )))
+            }
+            Member::String(s) => Member::String(s + &rhs.into_string_value()?),
+            Member::Char(c) => Member::String(c.to_string() + &rhs.into_string_value()?),
+            Member::NumI(n) => Member::NumI(checked(n.checked_add(rhs.into_inum_value()?), "+")?),
+            Member::Bool(_) => return Err(RispError::Type("Attempted to add to a bool".into())),
+            Member::Keyword(_) => {
+                return Err(RispError::Type("Attempted to add to a keyword".into()))
+            }
+            Member::List(_) => return Err(RispError::Type("Attempted to add to a list".into())),
+            Member::Seq(_) => return Err(RispError::Type("Attempted to add to a sequence".into())),
+            Member::Vector(_) => {
+                return Err(RispError::Type("Attempted to add to a vector".into()))
+            }
+            Member::Map(_) => return Err(RispError::Type("Attempted to add to a map".into())),
+            Member::Struct(_) => {
+                return Err(RispError::Type("Attempted to add to a struct".into()))
+            }
+            Member::Set(_) => return Err(RispError::Type("Attempted to add to a set".into())),
+            Member::Error(_) => return Err(RispError::Type("Attempted to add to an error".into())),
+            Member::Fun(_) | Member::Builtin(_) => {
+                return Err(RispError::Type("Attempted to add to a function".into()))
+            }
+            Member::Continuation(_) => {
+                return Err(RispError::Type("Attempted to add to a continuation".into()))
+            }
+            Member::Unit => res,
+            Member::SExpr(_) => {
+                return Err(RispError::Type(
+                    "Attempted to add to an unevaluated expression".into(),
+                ))
+            }
+        };
+    }
+    Ok(res)
+}
+
+fn fun_std_sub<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    let mut args = args.into_iter();
+    let first = args
+        .next()
+        .ok_or("Sub called with no arguments")?
+        .into_inum_value()?;
+    if args.len() == 0 {
+        return Ok(Member::NumI(checked(first.checked_neg(), "-")?));
+    }
+    let mut res = first;
+    for rhs in args {
+        res = checked(res.checked_sub(rhs.into_inum_value()?), "-")?;
+    }
+    Ok(Member::NumI(res))
+}
+
+/// Result of checked arithmetic, numbers are 32 bits and don't wrap around
+fn checked(result: Option<i32>, op: &str) -> Result<i32, RispError> {
+    result.ok_or_else(|| RispError::Runtime(format!("Integer overflow in {op}")))
+}
+
+fn fun_std_mul<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    let mut res: i32 = 1;
+    for arg in args {
+        res = checked(res.checked_mul(arg.into_inum_value()?), "*")?;
+    }
+    Ok(Member::NumI(res))
+}
+
+fn fun_std_eq<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    Ok(Member::Bool(args.windows(2).all(|w| w[0] == w[1])))
+}
+
+fn fun_std_lt<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    let nums = args
+        .into_iter()
+        .map(|arg| arg.into_inum_value())
+        .collect::<Result<Vec<i32>, _>>()?;
+    Ok(Member::Bool(nums.windows(2).all(|w| w[0] < w[1])))
+}
+
+fn expect_list<'a>(name: &str, args: Vec<Member<'a>>) -> Result<List<'a>, RispError> {
+    match <[Member<'a>; 1]>::try_from(args) {
+        Ok([Member::List(l)]) => Ok(l),
+        Ok([other]) => Err(RispError::Type(format!(
+            "{name} expects a list, got {other}"
+        ))),
+        Err(_) => Err(RispError::Arity(format!("{name} expects 1 argument"))),
+    }
+}
+
+/// `(range)`, `(range end)`, `(range start end)` or `(range start end step)`,
+/// without an end the sequence goes on until the numbers overflow
+fn fun_std_range<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    let nums = args
+        .into_iter()
+        .map(|arg| arg.into_inum_value())
+        .collect::<Result<Vec<i32>, _>>()?;
+    let (next, end, step) = match nums.as_slice() {
+        [] => (0, None, 1),
+        [end] => (0, Some(*end), 1),
+        [start, end] => (*start, Some(*end), 1),
+        [_, _, 0] => return Err(RispError::Runtime("range step cannot be 0".into())),
+        [start, end, step] => (*start, Some(*end), *step),
+        _ => return Err(RispError::Arity("range expects at most 3 arguments".into())),
+    };
+    Ok(Member::Seq(Rc::new(Seq::new(Step::Range {
+        next,
+        end,
+        step,
+    }))))
+}
+
+fn expect_vector<'a>(name: &str, arg: Option<Member<'a>>) -> Result<Vector<'a>, RispError> {
+    match arg {
+        Some(Member::Vector(v)) => Ok(v),
+        Some(other) => Err(RispError::Type(format!(
+            "{name} expects a vector, got {other}"
+        ))),
+        None => Err(RispError::Arity(format!("{name} expects a vector"))),
+    }
+}
+
+/// Vector and index in bounds at the start of the arguments
+fn expect_index<'a>(name: &str, args: &[Member<'a>]) -> Result<(Vector<'a>, usize), RispError> {
+    let [vector, index, ..] = args else {
+        return Err(RispError::Arity(format!(
+            "{name} expects a vector and an index"
+        )));
+    };
+    let Member::Vector(vector) = vector else {
+        return Err(RispError::Type(format!(
+            "{name} expects a vector, got {vector}"
+        )));
+    };
+    let index = index.clone().into_inum_value()?;
+    match usize::try_from(index) {
+        Ok(i) if i < vector.len() => Ok((vector.clone(), i)),
+        _ => Err(RispError::Runtime(format!(
+            "Index {index} is out of bounds for a vector of length {}",
+            vector.len()
+        ))),
+    }
+}
+
+/// `(slice vector start)` or `(slice vector start end)`
+fn fun_std_slice<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    let mut args = args.into_iter();
+    let vector = expect_vector("slice", args.next())?;
+    let mut bounds = Vec::new();
+    for arg in args {
+        bounds.push(arg.into_inum_value()?);
+    }
+    let (start, end) = match bounds.as_slice() {
+        [start] => (*start, vector.len() as i32),
+        [start, end] => (*start, *end),
+        _ => {
+            return Err(RispError::Arity(
+                "slice expects a vector, a start and an optional end".into(),
+            ))
+        }
+    };
+    match (usize::try_from(start), usize::try_from(end)) {
+        (Ok(start), Ok(end)) if start <= end && end <= vector.len() => Ok(Member::Vector(
+            vector
+                .iter()
+                .skip(start)
+                .take(end - start)
+                .cloned()
+                .collect(),
+        )),
+        _ => Err(RispError::Runtime(format!(
+            "Cannot slice {start}..{end} out of a vector of length {}",
+            vector.len()
+        ))),
+    }
+}
+
+/// `(conj collection value ...)` adds the values where they are cheapest to add:
+/// at the end of a vector, at the front of a list, and as `[key value]` pairs to a map
+fn fun_std_conj<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    let mut args = args.into_iter();
+    let collection = args.next().ok_or("conj expects a collection")?;
+    args.try_fold(collection, |collection, value| {
+        Ok(match (collection, value) {
+            (Member::Vector(v), value) => Member::Vector(v.push(value)),
+            (Member::List(l), value) => Member::List(l.cons(value)),
+            (Member::Set(s), value) => Member::Set(s.insert(value)),
+            (Member::Map(m), Member::Vector(pair)) if pair.len() == 2 => {
+                Member::Map(m.insert(pair.get(0).unwrap().clone(), pair.get(1).unwrap().clone()))
+            }
+            (Member::Map(_), other) => {
+                return Err(RispError::Type(format!(
+                    "conj expects [key value] pairs for a map, got {other}"
+                )))
+            }
+            (other, _) => {
+                return Err(RispError::Type(format!(
+                    "conj expects a collection, got {other}"
+                )))
+            }
+        })
+    })
+}
+
+/// Type a `defstruct` defined, named by a keyword
+fn struct_type<'a>(
+    name: Option<Member<'a>>,
+    context: &ProgContext<'a>,
+) -> Result<Rc<StructType<'a>>, RispError> {
+    match name {
+        Some(Member::Keyword(name)) => context
+            .types
+            .get(name)
+            .cloned()
+            .ok_or_else(|| RispError::Unbound(format!("Struct {name} not found"))),
+        _ => Err(RispError::Type("Structs are named by a keyword".into())),
+    }
+}
+
+fn expect_map<'a>(name: &str, arg: Option<Member<'a>>) -> Result<Map<'a>, RispError> {
+    match arg {
+        Some(Member::Map(m)) => Ok(m),
+        Some(other) => Err(RispError::Type(format!(
+            "{name} expects a map, got {other}"
+        ))),
+        None => Err(RispError::Arity(format!("{name} expects a map"))),
+    }
+}
+
+/// `(assoc map key value ...)`
+fn fun_std_assoc<'a>(map: Member<'a>, entries: Vec<Member<'a>>) -> OpResult<'a> {
+    if !entries.len().is_multiple_of(2) {
+        return Err(RispError::Arity(
+            "assoc expects a value for every key".into(),
+        ));
+    }
+    let mut entries = entries.into_iter();
+    // structs are updated field by field, and can't gain new ones
+    if let Member::Struct(mut s) = map {
+        while let (Some(field), Some(value)) = (entries.next(), entries.next()) {
+            let updated = match &field {
+                Member::Keyword(f) => s.with(f, value),
+                _ => None,
+            };
+            s =
+                Rc::new(updated.ok_or_else(|| {
+                    RispError::Type(format!("{} has no field {field}", s.ty.name))
+                })?);
+        }
+        return Ok(Member::Struct(s));
+    }
+    let mut map = expect_map("assoc", Some(map))?;
+    while let (Some(key), Some(value)) = (entries.next(), entries.next()) {
+        map = map.insert(key, value);
+    }
+    Ok(Member::Map(map))
+}
+
+/// `(get map key)`, `(get set value)` or `(get struct :field)`, returning `default` or `()` if it isn't there
+fn fun_std_get<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    let mut args = args.into_iter();
+    let (Some(coll), Some(key), default, None) =
+        (args.next(), args.next(), args.next(), args.next())
+    else {
+        return Err(RispError::Arity(
+            "get expects a collection, a key and an optional default".into(),
+        ));
+    };
+    let found = match coll {
+        Member::Map(m) => m.get(&key).cloned(),
+        Member::Set(s) => s.get(&key).cloned(),
+        Member::Struct(s) => match key {
+            Member::Keyword(field) => s.get(field).cloned(),
+            _ => None,
+        },
+        other => {
+            return Err(RispError::Type(format!(
+                "get expects a map or a set, got {other}"
+            )))
+        }
+    };
+    Ok(found.or(default).unwrap_or(Member::Unit))
+}
+
+fn fun_std_contains<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    match <[Member<'a>; 2]>::try_from(args) {
+        Ok([Member::Map(m), key]) => Ok(Member::Bool(m.contains_key(&key))),
+        Ok([Member::Set(s), value]) => Ok(Member::Bool(s.contains(&value))),
+        Ok([other, _]) => Err(RispError::Type(format!(
+            "contains? expects a map or a set, got {other}"
+        ))),
+        Err(_) => Err(RispError::Arity("contains? expects 2 arguments".into())),
+    }
+}
+
+/// Folds the sets of `args` into the first one with `op`
+fn fun_std_set_op<'a>(
+    name: &str,
+    args: Vec<Member<'a>>,
+    op: fn(Set<'a>, &Set<'a>) -> Set<'a>,
+) -> OpResult<'a> {
+    let mut sets = args.into_iter().map(|arg| match arg {
+        Member::Set(s) => Ok(s),
+        other => Err(RispError::Type(format!("{name} expects sets, got {other}"))),
+    });
+    let mut result = sets
+        .next()
+        .ok_or_else(|| RispError::Arity(format!("{name} expects at least one set")))??;
+    for set in sets {
+        result = op(result, &set?);
+    }
+    Ok(Member::Set(result))
+}
+
+fn fun_std_cons<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    match <[Member<'a>; 2]>::try_from(args) {
+        Ok([head, Member::List(tail)]) => Ok(Member::List(tail.cons(head))),
+        Ok([head, tail @ Member::Seq(_)]) => Ok(Member::Seq(Rc::new(Seq::cons(head, tail)))),
+        Ok([_, other]) => Err(RispError::Type(format!(
+            "cons expects a list as tail, got {other}"
+        ))),
+        Err(_) => Err(RispError::Arity("cons expects 2 arguments".into())),
+    }
+}
+
+fn expect_error<'a>(name: &str, args: Vec<Member<'a>>) -> Result<Rc<ErrorValue<'a>>, RispError> {
+    match <[Member<'a>; 1]>::try_from(args) {
+        Ok([Member::Error(e)]) => Ok(e),
+        Ok([other]) => Err(RispError::Type(format!(
+            "{name} expects an error, got {other}"
+        ))),
+        Err(_) => Err(RispError::Arity(format!("{name} expects 1 argument"))),
+    }
+}
+
+/// `(throw value)` throws any value, `(throw :kind message)` throws an error of that kind
+fn fun_std_throw<'a>(
+    args: Vec<Member<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    let mut args = args.into_iter();
+    match (args.next(), args.next(), args.next()) {
+        (Some(value), None, None) => Ok(Control::Unwind(Exit::Throw(value))),
+        (Some(Member::Keyword(kind)), Some(message), None) => {
+            let message = message.into_string_value()?;
+            Ok(Control::Unwind(Exit::Throw(Member::Error(Rc::new(
+                ErrorValue::new(kind, message, context.call_stack()),
+            )))))
+        }
+        _ => Err(RispError::Arity(
+            "throw expects a value, or a keyword and a message".into(),
+        )),
+    }
+}
+
+/// `(signal condition)` or `(signal [:kind] message)`, the condition is handed to the
+/// innermost handler for its kind
+fn fun_std_signal<'a>(
+    args: Vec<Member<'a>>,
+    severity: Severity,
+    default_kind: Ident<'a>,
+    frames: &mut Vec<Frame<'a>>,
+    context: &mut ProgContext<'a>,
+) -> Result<Control<'a>, RispError> {
+    let mut args = args.into_iter();
+    let condition = match (args.next(), args.next(), args.next()) {
+        (Some(condition @ Member::Error(_)), None, None) => condition,
+        (Some(Member::Keyword(kind)), Some(message), None) => {
+            let message = message.into_string_value()?;
+            Member::Error(Rc::new(ErrorValue::new(
+                kind,
+                message,
+                context.call_stack(),
+            )))
+        }
+        (Some(message), None, None) => {
+            let message = message.into_string_value()?;
+            Member::Error(Rc::new(ErrorValue::new(
+                default_kind,
+                message,
+                context.call_stack(),
+            )))
+        }
+        _ => {
+            return Err(RispError::Arity(
+                "Conditions are signalled with a condition, or a message and an optional kind"
+                    .into(),
+            ))
+        }
+    };
+    let top = frames.len();
+    signal(condition, severity, top, 0, frames, context)
 }
 
-#[allow(unused_variables)]
-fn fun_std_mul<'a>(args: Vec<Member<'a>>, context: &mut ProgContext<'a>) -> OpResult<'a> {
-    todo!()
+/// `(invoke-restart :name args...)` leaves everything up to the innermost `restart-case`
+/// with that restart and evaluates it in its place
+fn fun_std_invoke_restart<'a>(
+    args: Vec<Member<'a>>,
+    frames: &mut [Frame<'a>],
+) -> Result<Control<'a>, RispError> {
+    let mut args = args.into_iter();
+    let Some(Member::Keyword(name)) = args.next() else {
+        return Err(RispError::Arity(
+            "invoke-restart requires the keyword of a restart".into(),
+        ));
+    };
+    frames
+        .iter()
+        .enumerate()
+        .rev()
+        .find_map(|(target, frame)| match frame {
+            Frame::Restarts(restarts) => restarts.iter().find(|r| r.name == name).map(|r| {
+                Control::Unwind(Exit::Restart {
+                    target,
+                    restart: r.clone(),
+                    args: args.clone().collect(),
+                })
+            }),
+            _ => None,
+        })
+        .ok_or_else(|| format!("No restart :{name} is active").into())
 }
@@ -1,22 +1,40 @@
 use core::fmt;
-use std::fmt::{Display, Formatter};
+use std::{
+    fmt::{Display, Formatter},
+    iter::Peekable,
+    str::CharIndices,
+};
 
-const SYMBOLS: [char; 3] = ['(', ')', '"'];
+const SYMBOLS: [char; 7] = ['(', ')', '[', ']', '{', '}', '"'];
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Symbol {
     LParen,
     RParen,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    /// `#{`, opening a set
+    HashBrace,
     Quote,
+    /// `#"`, opening an interpolated string
+    HashQuote,
 }
 
-impl<'a> TryInto<Symbol> for &'a str {
+impl TryInto<Symbol> for &str {
     type Error = ();
     fn try_into(self) -> Result<Symbol, Self::Error> {
         Ok(match self {
             "(" => Symbol::LParen,
             ")" => Symbol::RParen,
+            "[" => Symbol::LBracket,
+            "]" => Symbol::RBracket,
+            "{" => Symbol::LBrace,
+            "}" => Symbol::RBrace,
+            "#{" => Symbol::HashBrace,
             "\"" => Symbol::Quote,
+            "#\"" => Symbol::HashQuote,
             _ => return Err(()),
         })
     }
@@ -30,7 +48,13 @@ impl Display for Symbol {
             match self {
                 Self::LParen => "LPAR",
                 Self::RParen => "RPAR",
+                Self::LBracket => "LBRK",
+                Self::RBracket => "RBRK",
+                Self::LBrace => "LBRC",
+                Self::RBrace => "RBRC",
+                Self::HashBrace => "HBRC",
                 Self::Quote => "QUOT",
+                Self::HashQuote => "HQUO",
             }
         )
     }
@@ -60,40 +84,92 @@ impl Display for Token<'_> {
             f,
             "{}",
             match self.ty {
-                TokenType::Ident => format!("{}", self.data),
+                TokenType::Ident => self.data.to_string(),
                 TokenType::Symbol(_) => format!("SYM {}", self.data),
             }
         )
     }
 }
 
-impl<'a> Into<Token<'a>> for &'a str {
-    fn into(self) -> Token<'a> {
-        let ty = match TryInto::<Symbol>::try_into(self) {
+impl<'a> From<&'a str> for Token<'a> {
+    fn from(data: &'a str) -> Self {
+        let ty = match TryInto::<Symbol>::try_into(data) {
             Ok(sym) => TokenType::Symbol(sym),
             Err(_) => TokenType::Ident,
         };
-        Token { ty, data: self }
+        Token { ty, data }
     }
 }
 
+/// Splits the input on whitespace and symbols. The contents of a string literal are
+/// kept whole, whitespace included, as a single token between its two quotes.
+/// A `;` starting a token comments out the rest of the line
 pub fn tokenize<'a>(input: &'a str) -> Vec<Token<'a>> {
-    input
-        .split_whitespace()
-        .flat_map(|toks| {
-            // A whitespace split will yield something like "(add" or "10))("
-            toks.split_inclusive(&SYMBOLS).flat_map(|some_toks| {
-                // split inclusive leaves some idents with a ) after "foo)" so we have to split them
-                if some_toks.len() > 1 {
-                    let len = some_toks.len() - 1;
-                    let tail = &some_toks[len..];
-                    let rest = &some_toks[..len];
-                    if SYMBOLS.contains(&tail.chars().last().unwrap()) {
-                        return vec![rest.into(), tail.into()];
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => (),
+            ';' => while chars.next_if(|(_, c)| *c != '\n').is_some() {},
+            '"' => string_literal(input, start, start + 1, &mut chars, &mut tokens),
+            '#' if matches!(chars.peek(), Some((_, '"'))) => {
+                chars.next();
+                string_literal(input, start, start + 2, &mut chars, &mut tokens);
+            }
+            '#' if matches!(chars.peek(), Some((_, '{'))) => {
+                chars.next();
+                tokens.push(input[start..start + 2].into());
+            }
+            c if SYMBOLS.contains(&c) => tokens.push(input[start..start + 1].into()),
+            _ => {
+                // the char of a `#\(` literal may be a symbol or whitespace
+                if c == '#' && chars.next_if(|(_, c)| *c == '\\').is_some() {
+                    chars.next();
+                }
+                let mut end = input.len();
+                while let Some(&(i, c)) = chars.peek() {
+                    if c.is_whitespace() || SYMBOLS.contains(&c) {
+                        end = i;
+                        break;
                     }
+                    chars.next();
                 }
-                vec![some_toks.into()]
-            })
-        })
-        .collect()
+                tokens.push(input[start..end].into());
+            }
+        }
+    }
+    tokens
+}
+
+/// Pushes the opening quote of the literal at `start`, its contents up to the closing
+/// quote as a single token, and the closing quote if there is one
+fn string_literal<'a>(
+    input: &'a str,
+    start: usize,
+    body: usize,
+    chars: &mut Peekable<CharIndices>,
+    tokens: &mut Vec<Token<'a>>,
+) {
+    tokens.push(input[start..body].into());
+    let mut end = input.len();
+    let mut escaped = false;
+    for (i, c) in chars.by_ref() {
+        match c {
+            '"' if !escaped => {
+                end = i;
+                break;
+            }
+            '\\' => escaped = !escaped,
+            _ => escaped = false,
+        }
+    }
+    if end > body {
+        tokens.push(Token {
+            ty: TokenType::Ident,
+            data: &input[body..end],
+        });
+    }
+    if end < input.len() {
+        tokens.push(input[end..end + 1].into());
+    }
 }
@@ -0,0 +1,164 @@
+//! Risp, a small Lisp to run as a program or to embed in one. A host builds an
+//! [`Interpreter`], grants it what it needs through a [`ProgContext`], hands it
+//! Rust functions with [`Interpreter::define`] and evaluates source with it
+
+mod ast;
+mod console;
+mod convert;
+mod error;
+mod files;
+mod format;
+mod interp;
+mod lexer;
+mod list;
+mod map;
+mod pattern;
+mod prelude;
+mod printer;
+mod process;
+mod record;
+mod seq;
+mod sexpr;
+mod strings;
+mod trace;
+mod vector;
+
+use std::{fs, path::Path, rc::Rc};
+
+pub use ast::{Ident, Member};
+pub use convert::{FromRisp, IntoRisp};
+pub use error::{Error, ErrorKind};
+pub use files::FsAccess;
+pub use interp::{Arity, Builtin, Debugger, HostFn, ProgContext, DEFAULT_MAX_DEPTH};
+pub use trace::{stderr_tracer, Event, Level, Tracer};
+
+use ast::AST;
+use interp::{eval_program, intern_program};
+
+/// Evaluates Risp for a host. Every evaluation shares the same root scope, so what
+/// one defines the next sees
+pub struct Interpreter<'a> {
+    context: ProgContext<'a>,
+}
+
+impl<'a> Interpreter<'a> {
+    /// With the prelude, writing to stdout and granted no capability
+    pub fn new() -> Self {
+        Self::with_context(ProgContext::default().with_prelude())
+    }
+
+    pub fn with_context(context: ProgContext<'a>) -> Self {
+        Self { context }
+    }
+
+    pub fn context(&mut self) -> &mut ProgContext<'a> {
+        &mut self.context
+    }
+
+    /// Evaluates every form of `source` in order, returns the value of the last one.
+    /// The names of the program are interned, `source` only has to live for the call.
+    /// Interned names are never freed: each distinct name stays allocated until the
+    /// process exits, shared by every interpreter that uses it
+    pub fn eval_str(&mut self, source: &str) -> Result<Member<'a>, Error> {
+        let tree: AST = lexer::tokenize(source)
+            .try_into()
+            .map_err(|e| Error::new(ErrorKind::Parse, e))?;
+        let tree = intern_program(tree, &mut self.context);
+        Ok(eval_program(tree, &mut self.context)?)
+    }
+
+    /// Like [`Self::eval_str`] with the contents of a file
+    pub fn eval_file(&mut self, path: impl AsRef<Path>) -> Result<Member<'a>, Error> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+            .map_err(|e| Error::new(ErrorKind::Io, format!("{}: {e}", path.display())))?;
+        self.eval_str(&source)
+    }
+
+    /// Binds a function calling `fun` in the root scope, in place of any builtin or
+    /// function of the same name. Its arguments and result are converted with
+    /// [`FromRisp`] and [`IntoRisp`], and an error it returns is raised in the program
+    pub fn define<Args>(
+        &mut self,
+        name: &'a str,
+        doc: &'static str,
+        fun: impl HostFunction<'a, Args> + 'a,
+    ) {
+        let arity = Arity::Exactly(fun.arity());
+        self.context.register(Builtin::host(
+            name,
+            arity,
+            doc,
+            Rc::new(move |args, _| Ok(fun.call(args)?)),
+        ));
+    }
+
+    /// Like [`Self::define`] for a function taking the values as they are, as many
+    /// as `arity` allows
+    pub fn define_variadic(
+        &mut self,
+        name: &'a str,
+        arity: Arity,
+        doc: &'static str,
+        fun: impl Fn(Vec<Member<'a>>) -> Result<Member<'a>, Error> + 'a,
+    ) {
+        self.context.register(Builtin::host(
+            name,
+            arity,
+            doc,
+            Rc::new(move |args, _| Ok(fun(args)?)),
+        ));
+    }
+
+    /// Binds a variable in the root scope
+    pub fn set(&mut self, name: &'a str, value: impl IntoRisp<'a>) {
+        self.context.reg_var(name, value.into_risp());
+    }
+
+    /// Value of a variable bound in the root scope
+    pub fn get<T: FromRisp<'a>>(&self, name: &str) -> Result<T, Error> {
+        T::from_risp(self.context.get_var(name)?)
+    }
+}
+
+impl Default for Interpreter<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Rust function a program can call, see [`Interpreter::define`]. Implemented for
+/// closures of up to four arguments, `Args` only tells those apart
+pub trait HostFunction<'a, Args> {
+    fn arity(&self) -> usize;
+    fn call(&self, args: Vec<Member<'a>>) -> Result<Member<'a>, Error>;
+}
+
+macro_rules! host_function {
+    ($($arg:ident),*) => {
+        impl<'a, Fun, Ret, $($arg),*> HostFunction<'a, ($($arg,)*)> for Fun
+        where
+            Fun: Fn($($arg),*) -> Result<Ret, Error>,
+            Ret: IntoRisp<'a>,
+            $($arg: FromRisp<'a>,)*
+        {
+            fn arity(&self) -> usize {
+                <[&str]>::len(&[$(stringify!($arg)),*])
+            }
+
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn call(&self, args: Vec<Member<'a>>) -> Result<Member<'a>, Error> {
+                // the arity was checked before the call
+                let mut args = args.into_iter();
+                $(let $arg = $arg::from_risp(args.next().unwrap())?;)*
+                Ok(self($($arg),*)?.into_risp())
+            }
+        }
+    };
+}
+
+host_function!();
+host_function!(A);
+host_function!(A, B);
+host_function!(A, B, C);
+host_function!(A, B, C, D);
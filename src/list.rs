@@ -0,0 +1,118 @@
+use core::fmt;
+use std::{fmt::Formatter, rc::Rc};
+
+use crate::ast::{drop_values, Member};
+
+/// Immutable singly linked list, tails are shared between lists built on top of each other
+#[derive(Clone, Default)]
+pub struct List<'a> {
+    head: Option<Rc<Node<'a>>>,
+}
+
+struct Node<'a> {
+    value: Member<'a>,
+    next: List<'a>,
+}
+
+impl<'a> List<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cons(&self, value: Member<'a>) -> Self {
+        Self {
+            head: Some(Rc::new(Node {
+                value,
+                next: self.clone(),
+            })),
+        }
+    }
+
+    pub fn first(&self) -> Option<&Member<'a>> {
+        self.head.as_ref().map(|node| &node.value)
+    }
+
+    pub fn rest(&self) -> Self {
+        match &self.head {
+            Some(node) => node.next.clone(),
+            None => Self::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.is_none()
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn iter(&self) -> Iter<'_, 'a> {
+        Iter {
+            next: self.head.as_deref(),
+        }
+    }
+}
+
+impl<'a> List<'a> {
+    /// Moves the values of the nodes only this list holds into `values`, see [`drop_values`]
+    pub fn take_values(&mut self, values: &mut Vec<Member<'a>>) {
+        let mut head = self.head.take();
+        while let Some(node) = head {
+            match Rc::try_unwrap(node) {
+                Ok(Node { value, mut next }) => {
+                    values.push(value);
+                    head = next.head.take();
+                }
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+impl Drop for List<'_> {
+    // the default drop recurses once per node and overflows the stack on long lists,
+    // and once per list nested in a value
+    fn drop(&mut self) {
+        let mut values = Vec::new();
+        self.take_values(&mut values);
+        drop_values(values);
+    }
+}
+
+impl PartialEq for List<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for List<'_> {}
+
+impl<'a> FromIterator<Member<'a>> for List<'a> {
+    fn from_iter<T: IntoIterator<Item = Member<'a>>>(iter: T) -> Self {
+        let values: Vec<Member<'a>> = iter.into_iter().collect();
+        values
+            .into_iter()
+            .rev()
+            .fold(Self::new(), |list, value| list.cons(value))
+    }
+}
+
+pub struct Iter<'l, 'a> {
+    next: Option<&'l Node<'a>>,
+}
+
+impl<'l, 'a> Iterator for Iter<'l, 'a> {
+    type Item = &'l Member<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.next?;
+        self.next = node.next.head.as_deref();
+        Some(&node.value)
+    }
+}
+
+impl fmt::Debug for List<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
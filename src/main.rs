@@ -1,33 +1,134 @@
-mod ast;
-mod interp;
-mod lexer;
-mod sexpr;
+use std::io::{self, Write};
 
-use std::{
-    fs,
-    io::{BufReader, Read},
-};
-
-use interp::interpret;
-
-use crate::ast::AST;
+use language::{stderr_tracer, Debugger, ErrorKind, FsAccess, Interpreter, Level, ProgContext};
 
 type Result<T> = std::result::Result<T, ()>;
 
+struct Options {
+    path: String,
+    max_depth: usize,
+    /// Ask which restart to invoke when an error isn't handled
+    debug: bool,
+    /// What evaluation is traced to stderr
+    trace: Level,
+    /// Let the program use the file system, confined to `fs_root` if there is one
+    allow_fs: bool,
+    fs_root: Option<String>,
+    /// Let the program start processes with `run`
+    allow_run: bool,
+    /// Start without the functions of the prelude
+    no_prelude: bool,
+    /// Arguments after the path of the program, its `*argv*`
+    argv: Vec<String>,
+}
+
 fn main() -> Result<()> {
-    parse_file("./test.risp")?;
+    let mut options = Options {
+        path: "./test.risp".to_string(),
+        max_depth: language::DEFAULT_MAX_DEPTH,
+        debug: false,
+        trace: Level::Off,
+        allow_fs: false,
+        fs_root: None,
+        allow_run: false,
+        no_prelude: false,
+        argv: Vec::new(),
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--max-depth" => {
+                options.max_depth = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or_else(|| eprintln!("--max-depth needs a number"))?;
+            }
+            "--debug" => options.debug = true,
+            // each `-v` traces more, `--trace` everything
+            "-v" | "--verbose" => {
+                options.trace = match options.trace {
+                    Level::Off => Level::Forms,
+                    _ => Level::Calls,
+                }
+            }
+            "--trace" => options.trace = Level::Calls,
+            "--allow-fs" => options.allow_fs = true,
+            "--fs-root" => {
+                options.allow_fs = true;
+                options.fs_root = Some(
+                    args.next()
+                        .ok_or_else(|| eprintln!("--fs-root needs a directory"))?,
+                );
+            }
+            "--allow-run" => options.allow_run = true,
+            "--no-prelude" => options.no_prelude = true,
+            // whatever follows the program is for the program
+            _ => {
+                options.path = arg;
+                options.argv = args.collect();
+                break;
+            }
+        }
+    }
+    let code = parse_file(&options)?;
+    if code != 0 {
+        std::process::exit(code);
+    }
     Ok(())
 }
 
-fn parse_file(path: &str) -> Result<()> {
-    let file = fs::File::open(path).map_err(|e| println!("{e}"))?;
-    let mut reader = BufReader::new(file);
-    let mut text = String::new();
-    reader
-        .read_to_string(&mut text)
-        .map_err(|e| println!("{e}"))?;
-    let tokens = lexer::tokenize(&text);
-    let tree: AST<'_> = tokens.try_into().map_err(|e| println!("{e}"))?;
-    interpret(tree);
-    Ok(())
+/// Lists the restarts available for an unhandled error, and lets the user pick one
+/// when `interactive`
+fn debugger<'a>(interactive: bool) -> Debugger<'a> {
+    Box::new(move |condition, restarts| {
+        // without restarts there is nothing to pick, the error is reported once the program stops
+        if restarts.is_empty() {
+            return None;
+        }
+        eprintln!("Unhandled :{} {}", condition.kind, condition.message());
+        eprintln!("Available restarts:");
+        for (i, name) in restarts.iter().enumerate() {
+            eprintln!("  {i}: :{name}");
+        }
+        if !interactive {
+            return None;
+        }
+        eprint!("Restart to invoke, empty to abort: ");
+        io::stderr().flush().ok()?;
+        let mut line = String::new();
+        io::stdin().read_line(&mut line).ok()?;
+        line.trim().parse().ok()
+    })
+}
+
+/// Runs the program, returns its exit code, 1 if an error went unhandled
+fn parse_file(options: &Options) -> Result<i32> {
+    let mut context = ProgContext::default()
+        .with_max_depth(options.max_depth)
+        .with_debugger(debugger(options.debug))
+        .with_tracer(options.trace, stderr_tracer())
+        .with_args(options.argv.clone());
+    if options.allow_fs {
+        let access = match &options.fs_root {
+            Some(root) => FsAccess::within(root).map_err(|e| eprintln!("{root}: {e}"))?,
+            None => FsAccess::unrestricted(),
+        };
+        context = context.with_filesystem(access);
+    }
+    if !options.no_prelude {
+        context = context.with_prelude();
+    }
+    if options.allow_run {
+        context = context.with_processes();
+    }
+    match Interpreter::with_context(context).eval_file(&options.path) {
+        Ok(_) => Ok(0),
+        Err(e) => match e.kind() {
+            ErrorKind::Exit(code) => Ok(*code),
+            _ => {
+                eprintln!("{e}");
+                Ok(1)
+            }
+        },
+    }
 }
@@ -0,0 +1,430 @@
+use core::fmt;
+use std::{
+    cell::OnceCell,
+    collections::hash_map::DefaultHasher,
+    fmt::Formatter,
+    hash::{Hash, Hasher},
+    rc::Rc,
+    slice,
+};
+
+use crate::ast::{drop_values, Member};
+
+const BITS: u32 = 5;
+const MASK: u64 = (1 << BITS) - 1;
+
+/// `{:a 1 :b 2}`, immutable hash array mapped trie. Each level picks a child with 5 bits
+/// of the key's hash, and updated maps share every node but the ones on the path to
+/// what changed
+#[derive(Clone, Default)]
+pub struct Map<'a> {
+    len: usize,
+    root: Option<Rc<Node<'a>>>,
+    /// Hash of the entries, worked out the first time the map is hashed
+    hash: OnceCell<u64>,
+}
+
+enum Node<'a> {
+    /// Only the children whose bit is set in `bitmap` are stored, in order
+    Branch {
+        bitmap: u32,
+        children: Vec<Rc<Node<'a>>>,
+    },
+    /// Entries whose keys all have this hash
+    Leaf {
+        hash: u64,
+        entries: Vec<(Member<'a>, Member<'a>)>,
+    },
+}
+
+/// Hashed with fixed keys, so that maps and sets iterate in the same order on every run
+fn hash_of(key: &Member) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Bit of the child the hash goes to at `shift`, and its position among the children
+fn slot(bitmap: u32, hash: u64, shift: u32) -> (u32, usize) {
+    let bit = 1 << ((hash >> shift) & MASK);
+    (bit, (bitmap & (bit - 1)).count_ones() as usize)
+}
+
+impl<'a> Node<'a> {
+    fn get(&self, hash: u64, shift: u32, key: &Member<'a>) -> Option<&(Member<'a>, Member<'a>)> {
+        match self {
+            Self::Branch { bitmap, children } => {
+                let (bit, index) = slot(*bitmap, hash, shift);
+                match bitmap & bit {
+                    0 => None,
+                    _ => children[index].get(hash, shift + BITS, key),
+                }
+            }
+            Self::Leaf { entries, .. } => entries.iter().find(|(k, _)| k == key),
+        }
+    }
+
+    /// Entries whose keys have this hash
+    fn entries(&self, hash: u64, shift: u32) -> &[(Member<'a>, Member<'a>)] {
+        match self {
+            Self::Branch { bitmap, children } => {
+                let (bit, index) = slot(*bitmap, hash, shift);
+                match bitmap & bit {
+                    0 => &[],
+                    _ => children[index].entries(hash, shift + BITS),
+                }
+            }
+            Self::Leaf { hash: own, entries } if *own == hash => entries,
+            Self::Leaf { .. } => &[],
+        }
+    }
+
+    /// Node with the entry added, and whether it replaced an existing one
+    fn insert(
+        self: &Rc<Self>,
+        hash: u64,
+        shift: u32,
+        key: Member<'a>,
+        value: Member<'a>,
+    ) -> (Rc<Self>, bool) {
+        match &**self {
+            Self::Branch { bitmap, children } => {
+                let (bit, index) = slot(*bitmap, hash, shift);
+                let mut children = children.clone();
+                let replaced = match bitmap & bit {
+                    0 => {
+                        let entries = vec![(key, value)];
+                        children.insert(index, Rc::new(Self::Leaf { hash, entries }));
+                        false
+                    }
+                    _ => {
+                        let (child, replaced) =
+                            children[index].insert(hash, shift + BITS, key, value);
+                        children[index] = child;
+                        replaced
+                    }
+                };
+                let bitmap = bitmap | bit;
+                (Rc::new(Self::Branch { bitmap, children }), replaced)
+            }
+            Self::Leaf { hash: own, entries } if *own == hash => {
+                let mut entries = entries.clone();
+                let replaced = match entries.iter_mut().find(|(k, _)| *k == key) {
+                    Some(entry) => {
+                        entry.1 = value;
+                        true
+                    }
+                    None => {
+                        entries.push((key, value));
+                        false
+                    }
+                };
+                (Rc::new(Self::Leaf { hash, entries }), replaced)
+            }
+            Self::Leaf { hash: own, .. } => {
+                // the hashes differ from here on, the leaf moves down a branch
+                let (bit, _) = slot(0, *own, shift);
+                let branch = Rc::new(Self::Branch {
+                    bitmap: bit,
+                    children: vec![self.clone()],
+                });
+                branch.insert(hash, shift, key, value)
+            }
+        }
+    }
+
+    /// Node with the key removed, `None` if it was the last entry
+    fn remove(self: &Rc<Self>, hash: u64, shift: u32, key: &Member<'a>) -> Option<Rc<Self>> {
+        match &**self {
+            Self::Branch { bitmap, children } => {
+                let (bit, index) = slot(*bitmap, hash, shift);
+                let mut children = children.clone();
+                let mut bitmap = *bitmap;
+                match children[index].remove(hash, shift + BITS, key) {
+                    Some(child) => children[index] = child,
+                    None => {
+                        children.remove(index);
+                        bitmap &= !bit;
+                    }
+                }
+                match bitmap {
+                    0 => None,
+                    _ => Some(Rc::new(Self::Branch { bitmap, children })),
+                }
+            }
+            Self::Leaf { hash, entries } => {
+                let entries: Vec<_> = entries.iter().filter(|(k, _)| k != key).cloned().collect();
+                match entries.is_empty() {
+                    true => None,
+                    false => Some(Rc::new(Self::Leaf {
+                        hash: *hash,
+                        entries,
+                    })),
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Map<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn get_key_value(&self, key: &Member<'a>) -> Option<&(Member<'a>, Member<'a>)> {
+        self.root.as_ref()?.get(hash_of(key), 0, key)
+    }
+
+    pub fn get(&self, key: &Member<'a>) -> Option<&Member<'a>> {
+        self.get_key_value(key).map(|(_, value)| value)
+    }
+
+    pub fn contains_key(&self, key: &Member<'a>) -> bool {
+        self.get_key_value(key).is_some()
+    }
+
+    pub fn insert(&self, key: Member<'a>, value: Member<'a>) -> Self {
+        let hash = hash_of(&key);
+        match &self.root {
+            None => Self {
+                len: 1,
+                root: Some(Rc::new(Node::Leaf {
+                    hash,
+                    entries: vec![(key, value)],
+                })),
+                hash: OnceCell::new(),
+            },
+            Some(root) => {
+                let (root, replaced) = root.insert(hash, 0, key, value);
+                Self {
+                    len: if replaced { self.len } else { self.len + 1 },
+                    root: Some(root),
+                    hash: OnceCell::new(),
+                }
+            }
+        }
+    }
+
+    pub fn remove(&self, key: &Member<'a>) -> Self {
+        match &self.root {
+            Some(root) if self.contains_key(key) => Self {
+                len: self.len - 1,
+                root: root.remove(hash_of(key), 0, key),
+                hash: OnceCell::new(),
+            },
+            _ => self.clone(),
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, 'a> {
+        Iter {
+            nodes: vec![self.root.as_slice().iter()],
+            entries: [].iter(),
+        }
+    }
+
+    /// Moves the keys and values only this map holds into `values`, see [`drop_values`]
+    pub fn take_values(&mut self, values: &mut Vec<Member<'a>>) {
+        self.len = 0;
+        let mut nodes: Vec<_> = self.root.take().into_iter().collect();
+        while let Some(node) = nodes.pop() {
+            match Rc::try_unwrap(node) {
+                Ok(Node::Branch { children, .. }) => nodes.extend(children),
+                Ok(Node::Leaf { entries, .. }) => {
+                    values.extend(entries.into_iter().flat_map(|(key, value)| [key, value]))
+                }
+                Err(_) => (),
+            }
+        }
+    }
+
+    /// Pairs the keys and values with the ones of `other` they have to equal, for comparing
+    /// maps without recursing into the maps nested in them. False if some key has no match
+    pub fn pair_entries<'m>(
+        &'m self,
+        other: &'m Self,
+        pairs: &mut Vec<(&'m Member<'a>, &'m Member<'a>)>,
+    ) -> bool {
+        if self.len != other.len {
+            return false;
+        }
+        let Some(root) = &other.root else {
+            return true;
+        };
+        for (key, value) in self.iter() {
+            match root.entries(hash_of(key), 0) {
+                [(other_key, other_value)] => {
+                    pairs.extend([(key, other_key), (value, other_value)])
+                }
+                // keys with the same hash are told apart by comparing them
+                entries => match entries.iter().find(|(k, _)| k == key) {
+                    Some((_, other_value)) => pairs.push((value, other_value)),
+                    None => return false,
+                },
+            }
+        }
+        true
+    }
+
+    /// Whether the hash of the entries has been worked out, see [`Map::hash_code`]
+    pub fn is_hashed(&self) -> bool {
+        self.hash.get().is_some()
+    }
+
+    /// Hash of the entries, which doesn't depend on the order they are stored in. It is
+    /// kept by the map, so the maps nested in its entries are only hashed once
+    pub fn hash_code(&self) -> u64 {
+        *self.hash.get_or_init(|| {
+            let mut hasher = DefaultHasher::new();
+            hash_unordered(self.iter(), &mut hasher);
+            hasher.finish()
+        })
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Member<'a>> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Member<'a>> {
+        self.iter().map(|(_, value)| value)
+    }
+}
+
+pub struct Iter<'m, 'a> {
+    /// Children left to visit at each level above the current leaf
+    nodes: Vec<slice::Iter<'m, Rc<Node<'a>>>>,
+    entries: slice::Iter<'m, (Member<'a>, Member<'a>)>,
+}
+
+impl<'m, 'a> Iterator for Iter<'m, 'a> {
+    type Item = (&'m Member<'a>, &'m Member<'a>);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((key, value)) = self.entries.next() {
+                return Some((key, value));
+            }
+            match self.nodes.last_mut()?.next().map(|node| &**node) {
+                Some(Node::Branch { children, .. }) => self.nodes.push(children.iter()),
+                Some(Node::Leaf { entries, .. }) => self.entries = entries.iter(),
+                None => {
+                    self.nodes.pop();
+                }
+            }
+        }
+    }
+}
+
+impl<'a> FromIterator<(Member<'a>, Member<'a>)> for Map<'a> {
+    fn from_iter<T: IntoIterator<Item = (Member<'a>, Member<'a>)>>(iter: T) -> Self {
+        iter.into_iter()
+            .fold(Self::new(), |map, (key, value)| map.insert(key, value))
+    }
+}
+
+impl PartialEq for Map<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl Eq for Map<'_> {}
+
+impl fmt::Debug for Map<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl Drop for Map<'_> {
+    // values nested in values would otherwise be dropped recursively
+    fn drop(&mut self) {
+        let mut values = Vec::new();
+        self.take_values(&mut values);
+        drop_values(values);
+    }
+}
+
+/// `#{1 2 3}`, a map from each value to `()`
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Set<'a>(Map<'a>);
+
+impl<'a> Set<'a> {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// The value of the set equal to `value`
+    pub fn get(&self, value: &Member<'a>) -> Option<&Member<'a>> {
+        self.0.get_key_value(value).map(|(value, _)| value)
+    }
+
+    pub fn contains(&self, value: &Member<'a>) -> bool {
+        self.0.contains_key(value)
+    }
+
+    pub fn insert(&self, value: Member<'a>) -> Self {
+        Self(self.0.insert(value, Member::Unit))
+    }
+
+    pub fn remove(&self, value: &Member<'a>) -> Self {
+        Self(self.0.remove(value))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Member<'a>> {
+        self.0.keys()
+    }
+
+    pub fn pair_values<'m>(
+        &'m self,
+        other: &'m Self,
+        pairs: &mut Vec<(&'m Member<'a>, &'m Member<'a>)>,
+    ) -> bool {
+        self.0.pair_entries(&other.0, pairs)
+    }
+
+    pub fn is_hashed(&self) -> bool {
+        self.0.is_hashed()
+    }
+
+    pub fn hash_code(&self) -> u64 {
+        self.0.hash_code()
+    }
+
+    pub fn take_values(&mut self, values: &mut Vec<Member<'a>>) {
+        self.0.take_values(values)
+    }
+}
+
+impl<'a> FromIterator<Member<'a>> for Set<'a> {
+    fn from_iter<T: IntoIterator<Item = Member<'a>>>(iter: T) -> Self {
+        Self(
+            iter.into_iter()
+                .map(|value| (value, Member::Unit))
+                .collect(),
+        )
+    }
+}
+
+impl fmt::Debug for Set<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+/// Hash of a collection that doesn't depend on the order its items are visited in,
+/// as equal maps and sets may store them in different orders
+pub fn hash_unordered<T: Hash, H: Hasher>(items: impl Iterator<Item = T>, state: &mut H) {
+    let mut len = 0;
+    let combined = items.fold(0u64, |combined, item| {
+        let mut hasher = DefaultHasher::new();
+        item.hash(&mut hasher);
+        len += 1;
+        combined.wrapping_add(hasher.finish())
+    });
+    len.hash(state);
+    combined.hash(state);
+}
@@ -0,0 +1,392 @@
+use core::fmt;
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+};
+
+use crate::{
+    ast::{Ident, Member, SExpr},
+    error::RispError,
+    interp::ProgContext,
+};
+
+/// Patterns left without a value while binding, along with the default expression
+/// whose value they still have to be bound to
+pub type Pending<'a> = Vec<(Pattern<'a>, Member<'a>)>;
+
+/// What a value matched against a pattern binds
+#[derive(Debug, Default)]
+pub struct Bound<'a> {
+    pub vars: Vec<(Ident<'a>, Member<'a>)>,
+    pub pending: Pending<'a>,
+}
+
+impl<'a> Bound<'a> {
+    /// Registers the variables in the current scope and hands back what is still pending
+    pub fn commit(self, context: &mut ProgContext<'a>) -> Pending<'a> {
+        for (ident, value) in self.vars {
+            context.reg_var(ident, value);
+        }
+        self.pending
+    }
+}
+
+/// Shape a value is destructured with by `let`, `def` parameters and `match`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern<'a> {
+    Bind(Ident<'a>),
+    /// `_`, matches anything without binding it
+    Ignore,
+    /// Number, string, char, bool or keyword matching only an equal value
+    Literal(Member<'a>),
+    List(ListPattern<'a>),
+    /// `{:key pattern ... :or {name default ...}}`, matches maps having every key,
+    /// whose values match the patterns, and structs having every field.
+    /// A key whose pattern is a name given a default under `:or` may be missing
+    Map(Vec<(Member<'a>, Pattern<'a>, Option<Member<'a>>)>),
+}
+
+/// `(a (b c) &optional d (e default) &rest more &key f (g default) :as whole)`,
+/// `[...]` may be used instead of parentheses and `&` instead of `&rest`.
+/// Matches vectors as well as lists
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListPattern<'a> {
+    required: Vec<Pattern<'a>>,
+    optional: Vec<(Pattern<'a>, Member<'a>)>,
+    rest: Option<Box<Pattern<'a>>>,
+    keys: Vec<(Ident<'a>, Member<'a>)>,
+    whole: Option<Ident<'a>>,
+}
+
+impl<'a> Pattern<'a> {
+    pub fn parse(member: Member<'a>) -> Result<Self, RispError> {
+        match member {
+            Member::Ident("_") => Ok(Self::Ignore),
+            Member::Ident(i) => Ok(Self::Bind(i)),
+            // `{...}` reads as a call to hash-map
+            Member::SExpr(s) if s.members.front() == Some(&Member::Ident("hash-map")) => {
+                let mut entries = Vec::new();
+                let mut defaults = Vec::new();
+                for (key, pattern) in Self::map_pairs(s)? {
+                    match (Self::parse(key)?, pattern) {
+                        (Self::Literal(Member::Keyword("or")), Member::SExpr(s))
+                            if s.members.front() == Some(&Member::Ident("hash-map")) =>
+                        {
+                            defaults.extend(Self::map_pairs(s)?)
+                        }
+                        (Self::Literal(Member::Keyword("or")), _) => {
+                            return Err(":or needs to be followed by {name default ...}".into())
+                        }
+                        (Self::Literal(key), pattern) => {
+                            entries.push((key, Self::parse(pattern)?, None))
+                        }
+                        _ => return Err("Map pattern keys must be literals".into()),
+                    }
+                }
+                for (name, default) in defaults {
+                    let name = Self::parse(name)?;
+                    let entry = entries
+                        .iter_mut()
+                        .find(|(_, pattern, _)| matches!(name, Self::Bind(_)) && *pattern == name)
+                        .ok_or_else(|| format!("{name} has a default but isn't bound by a key"))?;
+                    entry.2 = Some(default);
+                }
+                Ok(Self::Map(entries))
+            }
+            Member::SExpr(s) => Ok(Self::List(ListPattern::parse(s)?)),
+            literal @ (Member::NumI(_)
+            | Member::String(_)
+            | Member::Char(_)
+            | Member::Bool(_)
+            | Member::Keyword(_)) => Ok(Self::Literal(literal)),
+            other => Err(format!("{other} is not a valid pattern").into()),
+        }
+    }
+
+    /// Keys and values of a `{...}`, which has to have a value for every key
+    fn map_pairs(map: SExpr<'a>) -> Result<Vec<(Member<'a>, Member<'a>)>, RispError> {
+        let mut members = map.into_members().into_iter().skip(1);
+        let mut pairs = Vec::new();
+        while let Some(key) = members.next() {
+            let value = members
+                .next()
+                .ok_or_else(|| format!("{key} needs to be followed by a pattern"))?;
+            pairs.push((key, value));
+        }
+        Ok(pairs)
+    }
+
+    /// Collects what every ident of the pattern is bound to,
+    /// fails with a description of the mismatch if the value doesn't have the pattern's shape
+    pub fn bind(&self, value: Member<'a>, bound: &mut Bound<'a>) -> Result<(), String> {
+        match self {
+            Self::Bind(i) => bound.vars.push((i, value)),
+            Self::Ignore => (),
+            Self::Literal(literal) if *literal == value => (),
+            Self::Literal(_) => return Err(format!("expected {self}, got {value}")),
+            Self::List(list) => match value {
+                Member::List(l) => {
+                    list.bind(l.iter().cloned(), bound)
+                        .map_err(|problem| format!("{problem} for {self}"))?;
+                    if let Some(whole) = list.whole {
+                        bound.vars.push((whole, Member::List(l)));
+                    }
+                }
+                Member::Vector(v) => {
+                    list.bind(v.iter().cloned(), bound)
+                        .map_err(|problem| format!("{problem} for {self}"))?;
+                    if let Some(whole) = list.whole {
+                        bound.vars.push((whole, Member::Vector(v)));
+                    }
+                }
+                other => return Err(format!("expected a list for {self}, got {other}")),
+            },
+            Self::Map(entries) => match value {
+                Member::Map(map) => {
+                    for (key, pattern, default) in entries {
+                        match (map.get(key), default) {
+                            (Some(value), _) => pattern.bind(value.clone(), bound)?,
+                            (None, Some(default)) => {
+                                bound.pending.push((pattern.clone(), default.clone()))
+                            }
+                            (None, None) => {
+                                return Err(format!(
+                                    "missing key {} for {self}",
+                                    Self::Literal(key.clone())
+                                ))
+                            }
+                        }
+                    }
+                }
+                Member::Struct(s) => {
+                    for (key, pattern, default) in entries {
+                        let value = match key {
+                            Member::Keyword(field) => s.get(field),
+                            _ => None,
+                        };
+                        match (value, default) {
+                            (Some(value), _) => pattern.bind(value.clone(), bound)?,
+                            (None, Some(default)) => {
+                                bound.pending.push((pattern.clone(), default.clone()))
+                            }
+                            (None, None) => {
+                                return Err(format!(
+                                    "missing field {} for {self}",
+                                    Self::Literal(key.clone())
+                                ))
+                            }
+                        }
+                    }
+                }
+                other => return Err(format!("expected a map for {self}, got {other}")),
+            },
+        }
+        Ok(())
+    }
+}
+
+impl<'a> ListPattern<'a> {
+    pub fn parse(list: SExpr<'a>) -> Result<Self, RispError> {
+        #[derive(PartialEq, PartialOrd)]
+        enum Section {
+            Required,
+            Optional,
+            Rest,
+            Key,
+        }
+
+        let mut pattern = Self::default();
+        let mut section = Section::Required;
+        // marker starting the current section, and whether a pattern followed it yet
+        let mut marker = None;
+        let mut filled = false;
+        let mut members = list.into_members().into_iter().peekable();
+        // `[...]` reads as a call to vector
+        members.next_if_eq(&Member::Ident("vector"));
+        while let Some(member) = members.next() {
+            section = match member {
+                Member::Keyword("as") => {
+                    pattern.whole = Some(
+                        members
+                            .next()
+                            .and_then(Member::as_ident)
+                            .ok_or(":as needs to be followed by an ident")?,
+                    );
+                    section
+                }
+                Member::Ident(m @ ("&optional" | "&rest" | "&" | "&key")) => {
+                    let next = match m {
+                        "&optional" => Section::Optional,
+                        "&key" => Section::Key,
+                        _ => Section::Rest,
+                    };
+                    match marker {
+                        Some(previous) if !filled => {
+                            return Err(
+                                format!("{previous} needs to be followed by a pattern").into()
+                            )
+                        }
+                        Some(previous) if next <= section => {
+                            return Err(format!("{m} can't come after {previous}").into())
+                        }
+                        _ => (),
+                    }
+                    marker = Some(m);
+                    filled = false;
+                    next
+                }
+                member => {
+                    filled = true;
+                    match section {
+                        Section::Required => pattern.required.push(Pattern::parse(member)?),
+                        Section::Optional => {
+                            let (p, default) = Self::with_default(member)?;
+                            pattern.optional.push((Pattern::parse(p)?, default));
+                        }
+                        Section::Rest if pattern.rest.is_none() => {
+                            pattern.rest = Some(Box::new(Pattern::parse(member)?))
+                        }
+                        Section::Rest => return Err("&rest takes a single pattern".into()),
+                        Section::Key => {
+                            let (k, default) = Self::with_default(member)?;
+                            pattern.keys.push((
+                                k.as_ident().ok_or("&key parameters must be idents")?,
+                                default,
+                            ));
+                        }
+                    }
+                    section
+                }
+            };
+        }
+        match marker {
+            Some(m) if !filled => Err(format!("{m} needs to be followed by a pattern").into()),
+            _ => Ok(pattern),
+        }
+    }
+
+    /// `pattern` or `(pattern default)`, a missing default is `()`
+    fn with_default(member: Member<'a>) -> Result<(Member<'a>, Member<'a>), RispError> {
+        match member {
+            Member::SExpr(mut s) if s.members.len() == 2 => {
+                let default = s.members.pop_back().unwrap();
+                Ok((s.members.pop_back().unwrap(), default))
+            }
+            Member::SExpr(_) => Err("Patterns with a default are written (pattern default)".into()),
+            member => Ok((member, Member::Unit)),
+        }
+    }
+
+    pub fn bind(
+        &self,
+        values: impl IntoIterator<Item = Member<'a>>,
+        bound: &mut Bound<'a>,
+    ) -> Result<(), String> {
+        let mut values: Vec<Member<'a>> = values.into_iter().collect();
+        if values.len() < self.required.len() {
+            return Err(format!(
+                "missing {} value(s)",
+                self.required.len() - values.len()
+            ));
+        }
+        let mut values = values.drain(..);
+        for pattern in self.required.iter() {
+            pattern.bind(values.next().unwrap(), bound)?;
+        }
+        for (pattern, default) in self.optional.iter() {
+            match values.next() {
+                Some(value) => pattern.bind(value, bound)?,
+                None => bound.pending.push((pattern.clone(), default.clone())),
+            }
+        }
+
+        let mut rest = Vec::new();
+        let mut keys = HashMap::new();
+        while let Some(value) = values.next() {
+            match value {
+                Member::Keyword(k) if !self.keys.is_empty() => {
+                    if !self.keys.iter().any(|(key, _)| *key == k) {
+                        return Err(format!("unknown keyword :{k}"));
+                    }
+                    let value = values
+                        .next()
+                        .ok_or(format!("missing value for keyword :{k}"))?;
+                    keys.insert(k, value);
+                }
+                value if self.rest.is_some() => rest.push(value),
+                _ => return Err("too many values".to_string()),
+            }
+        }
+        if let Some(pattern) = &self.rest {
+            pattern.bind(Member::List(rest.into_iter().collect()), bound)?;
+        }
+        for (key, default) in self.keys.iter() {
+            match keys.remove(key) {
+                Some(value) => bound.vars.push((key, value)),
+                None => bound.pending.push((Pattern::Bind(key), default.clone())),
+            }
+        }
+        Ok(())
+    }
+
+    /// Signature of a function taking these parameters, as written in its `def`
+    pub fn signature(&self, name: Ident<'a>) -> String {
+        let mut parts = vec![name.to_string()];
+        parts.extend(self.parts());
+        format!("({})", parts.join(" "))
+    }
+
+    fn parts(&self) -> Vec<String> {
+        let mut parts: Vec<String> = self.required.iter().map(|p| p.to_string()).collect();
+        if !self.optional.is_empty() {
+            parts.push("&optional".to_string());
+            parts.extend(self.optional.iter().map(|(p, _)| p.to_string()));
+        }
+        if let Some(rest) = &self.rest {
+            parts.push("&rest".to_string());
+            parts.push(rest.to_string());
+        }
+        if !self.keys.is_empty() {
+            parts.push("&key".to_string());
+            parts.extend(self.keys.iter().map(|(k, _)| k.to_string()));
+        }
+        if let Some(whole) = self.whole {
+            parts.push(":as".to_string());
+            parts.push(whole.to_string());
+        }
+        parts
+    }
+}
+
+impl Display for Pattern<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Bind(i) => write!(f, "{i}"),
+            Self::Ignore => write!(f, "_"),
+            Self::Literal(literal) => write!(f, "{literal}"),
+            Self::List(list) => write!(f, "{list}"),
+            Self::Map(entries) => {
+                let mut parts: Vec<String> = entries
+                    .iter()
+                    .map(|(key, pattern, _)| format!("{} {pattern}", Self::Literal(key.clone())))
+                    .collect();
+                let defaults: Vec<String> = entries
+                    .iter()
+                    .filter_map(|(_, pattern, default)| {
+                        Some(format!("{pattern} {}", default.as_ref()?))
+                    })
+                    .collect();
+                if !defaults.is_empty() {
+                    parts.push(format!(":or {{{}}}", defaults.join(" ")));
+                }
+                write!(f, "{{{}}}", parts.join(" "))
+            }
+        }
+    }
+}
+
+impl Display for ListPattern<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "({})", self.parts().join(" "))
+    }
+}
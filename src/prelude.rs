@@ -0,0 +1,19 @@
+use crate::{
+    ast::AST,
+    interp::{interpret_sexpr, ProgContext},
+    lexer,
+};
+
+/// Functions written in Risp that programs start with, `map`, `filter`, `reduce`...
+const SOURCE: &str = include_str!("prelude.risp");
+
+/// Defines the functions of the prelude in the root scope of `context`
+pub fn load<'a>(context: &mut ProgContext<'a>) {
+    let source: &'a str = SOURCE;
+    let tree: AST<'a> = lexer::tokenize(source)
+        .try_into()
+        .expect("the prelude is valid Risp");
+    for form in tree.prog {
+        interpret_sexpr(form, context).expect("the prelude only defines functions");
+    }
+}
@@ -0,0 +1,313 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+use crate::{
+    ast::{escape, Member, SExpr},
+    error::RispError,
+};
+
+/// How strings and chars are printed, everything else prints the same either way
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    /// For people reading the output, strings and chars as their text
+    Display,
+    /// As written in code, so that the output can be read back. Lists and structs print
+    /// as the calls building them, values that can't be read print as `#<...>`
+    Write,
+}
+
+/// Width `pretty` fits lines in when it isn't given one
+pub const DEFAULT_WIDTH: usize = 80;
+
+/// How a char literal is written, by name if it's whitespace or invisible
+pub fn char_name(c: char) -> String {
+    match c {
+        ' ' => "space".to_string(),
+        '\n' => "newline".to_string(),
+        '\t' => "tab".to_string(),
+        '\r' => "return".to_string(),
+        '\0' => "nul".to_string(),
+        c => c.to_string(),
+    }
+}
+
+/// Element of a collection, entries of maps and structs are kept on the same line.
+/// Refers to the parts of the value by their index in [`Layout::parts`]
+enum Item {
+    One(usize),
+    Pair(usize, usize),
+}
+
+/// Value taken apart in the parts the printer lays out
+enum Part {
+    /// Printed the same at any width
+    Atom(String),
+    Compound {
+        open: String,
+        /// The items are arguments of a call, the first one is separated from the name
+        call: bool,
+        items: Vec<Item>,
+        close: &'static str,
+    },
+}
+
+/// A value and the values nested in it, each with the width it takes on a single line.
+/// Built and printed with explicit stacks, values can be nested deeper than the native
+/// stack allows
+struct Layout {
+    /// Nested values come after the ones holding them
+    parts: Vec<Part>,
+    widths: Vec<usize>,
+}
+
+/// What is left to print, innermost last
+enum Task<'l> {
+    Part(usize),
+    /// Part laid out at this indentation
+    Pretty(usize, usize),
+    Text(&'l str),
+    /// New line, then this many spaces
+    Break(usize),
+}
+
+impl Layout {
+    fn new<'a>(value: &Member<'a>, mode: Mode) -> Self {
+        let mut parts = vec![Part::Atom(String::new())];
+        let mut values = vec![(value.clone(), 0)];
+        while let Some((value, index)) = values.pop() {
+            let mut add = |value: Member<'a>| {
+                parts.push(Part::Atom(String::new()));
+                values.push((value, parts.len() - 1));
+                parts.len() - 1
+            };
+            let mut ones = |items: &mut dyn Iterator<Item = Member<'a>>| -> Vec<Item> {
+                items.map(|value| Item::One(add(value))).collect()
+            };
+            let (open, call, items, close): (String, _, _, _) = match &value {
+                Member::SExpr(s) => ("(".into(), false, ones(&mut s.members.iter().cloned()), ")"),
+                Member::List(l) => match mode {
+                    Mode::Display => ("(".into(), false, ones(&mut l.iter().cloned()), ")"),
+                    Mode::Write => ("(list".into(), true, ones(&mut l.iter().cloned()), ")"),
+                },
+                Member::Seq(s) => {
+                    let (values, complete) = s.realized();
+                    let mut values = values.into_iter();
+                    match (complete, mode) {
+                        (true, Mode::Display) => ("(".into(), false, ones(&mut values), ")"),
+                        (true, Mode::Write) => ("(list".into(), true, ones(&mut values), ")"),
+                        (false, Mode::Display) => {
+                            let mut values = values.chain([Member::Ident("...")]);
+                            ("(".into(), false, ones(&mut values), ")")
+                        }
+                        (false, Mode::Write) => {
+                            let mut values = values.chain([Member::Ident("...")]);
+                            ("#<sequence (".into(), false, ones(&mut values), ")>")
+                        }
+                    }
+                }
+                Member::Vector(v) => ("[".into(), false, ones(&mut v.iter().cloned()), "]"),
+                Member::Set(s) => ("#{".into(), false, ones(&mut s.iter().cloned()), "}"),
+                Member::Map(m) => {
+                    let items = m
+                        .iter()
+                        .map(|(key, value)| Item::Pair(add(key.clone()), add(value.clone())))
+                        .collect();
+                    ("{".into(), false, items, "}")
+                }
+                Member::Struct(s) => match mode {
+                    Mode::Display => {
+                        let items = s
+                            .entries()
+                            .map(|(field, value)| {
+                                Item::Pair(add(Member::Keyword(field)), add(value.clone()))
+                            })
+                            .collect();
+                        (format!("#{}{{", s.ty.name), false, items, "}")
+                    }
+                    // variants of a `deftype` are built by a function named after them
+                    Mode::Write => {
+                        let open = match s.ty.sum {
+                            Some(_) => format!("({}", s.ty.name),
+                            None => format!("(make-{}", s.ty.name),
+                        };
+                        (open, true, ones(&mut s.values.iter().cloned()), ")")
+                    }
+                },
+                value => {
+                    parts[index] = Part::Atom(atom(value, mode));
+                    continue;
+                }
+            };
+            parts[index] = Part::Compound {
+                open,
+                call,
+                items,
+                close,
+            };
+        }
+        // the parts nested in a compound come after it, and are measured before it
+        let mut widths = vec![0; parts.len()];
+        for index in (0..parts.len()).rev() {
+            widths[index] = match &parts[index] {
+                Part::Atom(text) => text.chars().count(),
+                Part::Compound {
+                    open,
+                    call,
+                    items,
+                    close,
+                } => {
+                    let spaces = match call {
+                        true => items.len(),
+                        false => items.len().saturating_sub(1),
+                    };
+                    let items: usize = items
+                        .iter()
+                        .map(|item| match item {
+                            Item::One(value) => widths[*value],
+                            Item::Pair(key, value) => widths[*key] + 1 + widths[*value],
+                        })
+                        .sum();
+                    open.chars().count() + spaces + items + close.chars().count()
+                }
+            };
+        }
+        Self { parts, widths }
+    }
+
+    /// Prints the tasks on `stack` until it's empty
+    fn print<'l>(&'l self, mut stack: Vec<Task<'l>>, width: usize, out: &mut String) {
+        while let Some(task) = stack.pop() {
+            let (index, indent) = match task {
+                Task::Text(text) => {
+                    out.push_str(text);
+                    continue;
+                }
+                Task::Break(indent) => {
+                    out.push('\n');
+                    out.push_str(&" ".repeat(indent));
+                    continue;
+                }
+                Task::Part(index) => (index, None),
+                Task::Pretty(index, indent) => (index, Some(indent)),
+            };
+            let (open, call, items, close) = match &self.parts[index] {
+                Part::Atom(text) => {
+                    out.push_str(text);
+                    continue;
+                }
+                Part::Compound {
+                    open,
+                    call,
+                    items,
+                    close,
+                } => (open, *call, items, close),
+            };
+            // too wide for the line, items go under the first one, which stays on the
+            // line of the delimiter
+            let inner = match indent {
+                Some(indent) if indent + self.widths[index] > width && !items.is_empty() => {
+                    Some(indent + open.chars().count() + usize::from(call))
+                }
+                _ => None,
+            };
+            out.push_str(open);
+            stack.push(Task::Text(close));
+            for (i, item) in items.iter().enumerate().rev() {
+                match item {
+                    Item::One(value) => stack.push(match inner {
+                        Some(inner) => Task::Pretty(*value, inner),
+                        None => Task::Part(*value),
+                    }),
+                    Item::Pair(key, value) => {
+                        stack.push(match inner {
+                            Some(inner) => Task::Pretty(*value, inner + self.widths[*key] + 1),
+                            None => Task::Part(*value),
+                        });
+                        stack.push(Task::Text(" "));
+                        stack.push(Task::Part(*key));
+                    }
+                }
+                match (i, inner) {
+                    (0, _) if call => stack.push(Task::Text(" ")),
+                    (0, _) => (),
+                    (_, Some(inner)) => stack.push(Task::Break(inner)),
+                    (_, None) => stack.push(Task::Text(" ")),
+                }
+            }
+        }
+    }
+}
+
+/// Values printed the same at any width
+fn atom(value: &Member, mode: Mode) -> String {
+    match value {
+        Member::Ident(i) => i.to_string(),
+        Member::String(s) => match mode {
+            Mode::Display => s.clone(),
+            Mode::Write => format!("\"{}\"", escape(s)),
+        },
+        Member::Char(c) => match mode {
+            Mode::Display => c.to_string(),
+            Mode::Write => format!("#\\{}", char_name(*c)),
+        },
+        Member::NumI(n) => n.to_string(),
+        Member::Bool(b) => b.to_string(),
+        Member::Keyword(k) => format!(":{k}"),
+        Member::Error(e) => format!("#<error :{} {}>", e.kind, e.message()),
+        Member::Fun(f) => format!("#<function {}>", f.name()),
+        Member::Builtin(b) => format!("#<builtin {}>", b.name),
+        Member::Continuation(c) => format!("{c:?}"),
+        Member::Unit => "()".to_string(),
+        _ => unreachable!("collections are printed item by item"),
+    }
+}
+
+/// Value on a single line
+pub fn print(value: &Member, mode: Mode) -> String {
+    let mut out = String::new();
+    Layout::new(value, mode).print(vec![Task::Part(0)], 0, &mut out);
+    out
+}
+
+/// Value laid out to fit in `width` columns where it can, collections too wide for
+/// a line get an item per line, aligned after their opening delimiter
+pub fn pretty(value: &Member, mode: Mode, width: usize) -> String {
+    let mut out = String::new();
+    Layout::new(value, mode).print(vec![Task::Pretty(0, 0)], width, &mut out);
+    out
+}
+
+/// Values print the way they are written, see [`Mode::Write`]
+impl Display for Member<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(&print(self, Mode::Write))
+    }
+}
+
+impl Display for SExpr<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(&print(&Member::SExpr(self.clone()), Mode::Write))
+    }
+}
+
+/// `(pretty value)` or `(pretty value width)`, the value written over as many lines as
+/// it takes to fit in the width
+pub fn fun_std_pretty<'a>(args: Vec<Member<'a>>) -> Result<Member<'a>, RispError> {
+    pretty_args(args).map(Member::String)
+}
+
+/// Pretty printed text of a value and an optional width, as `pretty` and `pprint` take them
+pub fn pretty_args(args: Vec<Member>) -> Result<String, RispError> {
+    let mut args = args.into_iter();
+    let (Some(value), width, None) = (args.next(), args.next(), args.next()) else {
+        return Err(RispError::Arity(
+            "pretty expects a value and an optional width".into(),
+        ));
+    };
+    let width = match width {
+        Some(width) => usize::try_from(width.into_inum_value()?)
+            .map_err(|_| RispError::Type("pretty expects a positive width".into()))?,
+        None => DEFAULT_WIDTH,
+    };
+    Ok(pretty(&value, Mode::Write, width))
+}
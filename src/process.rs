@@ -0,0 +1,102 @@
+use std::{env, process::Command};
+
+use crate::{
+    ast::Member,
+    error::RispError,
+    interp::{Arity, Builtin, ProgContext},
+    map::Map,
+};
+
+type OpResult<'a> = Result<Member<'a>, RispError>;
+
+fn expect_string<'a>(name: &str, what: &str, arg: Member<'a>) -> Result<String, RispError> {
+    match arg {
+        Member::String(s) => Ok(s),
+        other => Err(RispError::Type(format!(
+            "{name} expects {what} as a string, got {other}"
+        ))),
+    }
+}
+
+/// `()` for a variable that isn't set, or isn't unicode
+fn fun_std_getenv<'a>(name: &str, args: Vec<Member<'a>>) -> OpResult<'a> {
+    let [var] = <[Member<'a>; 1]>::try_from(args)
+        .map_err(|_| RispError::Arity("getenv expects a variable name".into()))?;
+    let var = expect_string(name, "a variable name", var)?;
+    Ok(env::var(var).map_or(Member::Unit, Member::String))
+}
+
+/// Unwinds the whole program, running the cleanups of `dynamic-wind` on the way
+fn fun_std_exit<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    let mut args = args.into_iter();
+    let code = match (args.next(), args.next()) {
+        (None, _) => 0,
+        (Some(Member::NumI(code)), None) => code,
+        (Some(Member::Bool(ok)), None) => i32::from(!ok),
+        (Some(other), None) => {
+            return Err(RispError::Type(format!(
+                "exit expects a number or a boolean, got {other}"
+            )))
+        }
+        _ => return Err(RispError::Arity("exit expects an optional code".into())),
+    };
+    Err(RispError::Exit(code))
+}
+
+fn fun_std_run<'a>(
+    name: &str,
+    args: Vec<Member<'a>>,
+    context: &mut ProgContext<'a>,
+) -> OpResult<'a> {
+    if !context.processes() {
+        return Err(RispError::Denied(
+            "run needs to start processes, which this program wasn't granted".into(),
+        ));
+    }
+    let mut args = args.into_iter();
+    let program = match args.next() {
+        Some(program) => expect_string(name, "a program", program)?,
+        None => return Err(RispError::Arity("run expects a program to run".into())),
+    };
+    let args = args
+        .map(|arg| expect_string(name, "arguments", arg))
+        .collect::<Result<Vec<_>, _>>()?;
+    let output = Command::new(&program)
+        .args(&args)
+        .output()
+        .map_err(|e| RispError::Io(format!("{program}: {e}")))?;
+    // no status when the process was killed by a signal
+    let status = output.status.code().map_or(Member::Unit, Member::NumI);
+    let text = |bytes: Vec<u8>| Member::String(String::from_utf8_lossy(&bytes).into_owned());
+    Ok(Member::Map(Map::from_iter([
+        (Member::Keyword("status"), status),
+        (Member::Keyword("stdout"), text(output.stdout)),
+        (Member::Keyword("stderr"), text(output.stderr)),
+    ])))
+}
+
+/// Builtins for the process the program runs in and the ones it starts. Only `run`
+/// needs a capability, reading the environment and exiting don't
+pub fn builtins<'a>() -> Vec<Builtin<'a>> {
+    use Arity::{AtLeast, Between, Exactly};
+    vec![
+        Builtin::value(
+            "getenv",
+            Exactly(1),
+            "(getenv name) value of an environment variable, () if unset",
+            |name, args, _| fun_std_getenv(name, args),
+        ),
+        Builtin::value(
+            "exit",
+            Between(0, 1),
+            "(exit [code]) ends the program with the code, 0 by default",
+            |_, args, _| fun_std_exit(args),
+        ),
+        Builtin::value(
+            "run",
+            AtLeast(1),
+            "(run program args...) runs a program, returns {:status :stdout :stderr}",
+            fun_std_run,
+        ),
+    ]
+}
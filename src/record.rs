@@ -0,0 +1,75 @@
+use core::fmt;
+use std::{fmt::Formatter, mem, rc::Rc};
+
+use crate::ast::{drop_values, Ident, Member};
+
+/// Runtime descriptor of the type defined by a `defstruct`, or of a variant of a `deftype`
+#[derive(Debug)]
+pub struct StructType<'a> {
+    pub name: Ident<'a>,
+    pub fields: Vec<Ident<'a>>,
+    /// Sum type this is a variant of
+    pub sum: Option<Ident<'a>>,
+}
+
+/// Value built by the constructor of a `defstruct`, holding one value per field
+#[derive(Clone)]
+pub struct Struct<'a> {
+    pub ty: Rc<StructType<'a>>,
+    pub values: Vec<Member<'a>>,
+}
+
+impl<'a> Struct<'a> {
+    fn index(&self, field: Ident<'a>) -> Option<usize> {
+        self.ty.fields.iter().position(|f| *f == field)
+    }
+
+    pub fn get(&self, field: Ident<'a>) -> Option<&Member<'a>> {
+        self.index(field).map(|i| &self.values[i])
+    }
+
+    /// Copy of the struct with `field` set to `value`, `None` if the type has no such field
+    pub fn with(&self, field: Ident<'a>, value: Member<'a>) -> Option<Self> {
+        let index = self.index(field)?;
+        let mut updated = self.clone();
+        updated.values[index] = value;
+        Some(updated)
+    }
+
+    /// Fields with their values, in the order they were declared
+    pub fn entries(&self) -> impl Iterator<Item = (Ident<'a>, &Member<'a>)> {
+        self.ty.fields.iter().copied().zip(self.values.iter())
+    }
+
+    /// Moves the values into `values`, see [`drop_values`]
+    pub fn take_values(&mut self, values: &mut Vec<Member<'a>>) {
+        values.append(&mut self.values)
+    }
+}
+
+impl Drop for Struct<'_> {
+    // values nested in values would otherwise be dropped recursively
+    fn drop(&mut self) {
+        drop_values(mem::take(&mut self.values));
+    }
+}
+
+impl PartialEq for Struct<'_> {
+    // structs of types defined apart are different, even with the same name and fields
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.ty, &other.ty) && self.values == other.values
+    }
+}
+
+impl Eq for Struct<'_> {}
+
+impl fmt::Debug for Struct<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "#{}{{", self.ty.name)?;
+        for (i, (field, value)) in self.entries().enumerate() {
+            let sep = if i == 0 { "" } else { " " };
+            write!(f, "{sep}:{field} {value:?}")?;
+        }
+        write!(f, "}}")
+    }
+}
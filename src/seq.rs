@@ -0,0 +1,265 @@
+use core::fmt;
+use std::{cell::RefCell, fmt::Formatter, mem, rc::Rc};
+
+use crate::{
+    ast::Member,
+    interp::{Continuation, Env},
+};
+
+/// Sequence whose cells are only computed once something asks for them.
+/// Realized cells are kept, so each step runs at most once
+pub struct Seq<'a>(RefCell<State<'a>>);
+
+#[derive(Clone)]
+enum State<'a> {
+    Unrealized(Step<'a>),
+    /// Step being computed by the interpreter, kept to start over if that fails
+    Realizing(Step<'a>),
+    Empty,
+    /// First value and the rest of the sequence, a list, a sequence or `()`
+    Cons(Member<'a>, Member<'a>),
+}
+
+/// How the next cell of a sequence is computed
+#[derive(Clone)]
+pub enum Step<'a> {
+    /// Body of a `lazy-seq`, evaluated with what was bound where it was written
+    Expr {
+        body: Member<'a>,
+        env: Env<'a>,
+    },
+    /// Body of a `generator`, run up to its first `yield`
+    Generator {
+        body: Member<'a>,
+        env: Env<'a>,
+    },
+    /// Generator suspended at a `yield`
+    Resume(Rc<Continuation<'a>>),
+    /// Values following `value` in `(iterate fun x)`
+    Iterate {
+        fun: Member<'a>,
+        value: Member<'a>,
+    },
+    Range {
+        next: i32,
+        end: Option<i32>,
+        step: i32,
+    },
+    Take {
+        n: usize,
+        seq: Member<'a>,
+    },
+    Drop {
+        n: usize,
+        seq: Member<'a>,
+    },
+    TakeWhile {
+        pred: Member<'a>,
+        seq: Member<'a>,
+    },
+}
+
+/// Front of a sequence
+pub enum Next<'a> {
+    Empty,
+    Cons(Member<'a>, Member<'a>),
+    /// This sequence has to be realized by the interpreter before the front is known
+    Unrealized(Rc<Seq<'a>>),
+}
+
+/// Front of a list, a lazy sequence or `()`, `None` for anything else
+pub fn next<'a>(member: &Member<'a>) -> Result<Option<Next<'a>>, String> {
+    Ok(Some(match member {
+        Member::List(l) => match l.first() {
+            Some(first) => Next::Cons(first.clone(), Member::List(l.rest())),
+            None => Next::Empty,
+        },
+        Member::Unit => Next::Empty,
+        Member::Seq(seq) => seq.front()?,
+        _ => return Ok(None),
+    }))
+}
+
+fn expect_next<'a>(member: &Member<'a>) -> Result<Next<'a>, String> {
+    next(member)?.ok_or_else(|| format!("{member} is not a sequence"))
+}
+
+impl<'a> Seq<'a> {
+    pub fn new(step: Step<'a>) -> Self {
+        Self(RefCell::new(State::Unrealized(step)))
+    }
+
+    pub fn cons(first: Member<'a>, rest: Member<'a>) -> Self {
+        Self(RefCell::new(State::Cons(first, rest)))
+    }
+
+    /// Realizes what can be without running any code, and tells which sequence the
+    /// interpreter has to realize otherwise
+    pub fn front(self: &Rc<Self>) -> Result<Next<'a>, String> {
+        loop {
+            let step = match &*self.0.borrow() {
+                State::Empty => return Ok(Next::Empty),
+                State::Cons(first, rest) => return Ok(Next::Cons(first.clone(), rest.clone())),
+                State::Realizing(_) => {
+                    return Err("Sequence needs its own value to be realized".into())
+                }
+                State::Unrealized(step) => step.clone(),
+            };
+            let state = match step {
+                Step::Range { next, end, step } => match end {
+                    Some(end) if (step > 0 && next >= end) || (step < 0 && next <= end) => {
+                        State::Empty
+                    }
+                    _ => State::Cons(
+                        Member::NumI(next),
+                        match next.checked_add(step) {
+                            Some(next) => {
+                                Member::Seq(Rc::new(Self::new(Step::Range { next, end, step })))
+                            }
+                            None => Member::Unit,
+                        },
+                    ),
+                },
+                Step::Take { n: 0, .. } => State::Empty,
+                Step::Take { n, seq } => match expect_next(&seq)? {
+                    Next::Empty => State::Empty,
+                    Next::Cons(first, rest) => State::Cons(
+                        first,
+                        Member::Seq(Rc::new(Self::new(Step::Take {
+                            n: n - 1,
+                            seq: rest,
+                        }))),
+                    ),
+                    unrealized => return Ok(unrealized),
+                },
+                Step::Drop { mut n, mut seq } => {
+                    loop {
+                        match expect_next(&seq)? {
+                            Next::Empty => break State::Empty,
+                            Next::Cons(first, rest) if n == 0 => break State::Cons(first, rest),
+                            Next::Cons(_, rest) => {
+                                n -= 1;
+                                seq = rest;
+                            }
+                            unrealized => {
+                                // what was dropped so far doesn't have to be walked again
+                                *self.0.borrow_mut() = State::Unrealized(Step::Drop { n, seq });
+                                return Ok(unrealized);
+                            }
+                        }
+                    }
+                }
+                Step::TakeWhile { seq, .. } => match expect_next(&seq)? {
+                    Next::Empty => State::Empty,
+                    Next::Cons(..) => return Ok(Next::Unrealized(self.clone())),
+                    unrealized => return Ok(unrealized),
+                },
+                Step::Expr { .. }
+                | Step::Generator { .. }
+                | Step::Resume(_)
+                | Step::Iterate { .. } => return Ok(Next::Unrealized(self.clone())),
+            };
+            *self.0.borrow_mut() = state;
+        }
+    }
+
+    /// Takes the step out for the interpreter to compute it
+    pub fn start(&self) -> Result<Step<'a>, String> {
+        let mut state = self.0.borrow_mut();
+        match &*state {
+            State::Unrealized(step) => {
+                let step = step.clone();
+                *state = State::Realizing(step.clone());
+                Ok(step)
+            }
+            State::Realizing(_) => Err("Sequence needs its own value to be realized".into()),
+            State::Empty | State::Cons(..) => Err("Sequence is already realized".into()),
+        }
+    }
+
+    /// Puts the step back after computing it failed
+    pub fn abort(&self) {
+        let mut state = self.0.borrow_mut();
+        if let State::Realizing(step) = &*state {
+            *state = State::Unrealized(step.clone());
+        }
+    }
+
+    /// Realizes the sequence as `value`, the sequence the step computed.
+    /// If that's a sequence that isn't realized yet, it's handed back to be realized first
+    pub fn fill(&self, value: &Member<'a>) -> Result<Option<Rc<Seq<'a>>>, String> {
+        let state = match expect_next(value)
+            .map_err(|_| format!("Expected a sequence to realize, got {value}"))?
+        {
+            Next::Empty => State::Empty,
+            Next::Cons(first, rest) => State::Cons(first, rest),
+            Next::Unrealized(seq) => return Ok(Some(seq)),
+        };
+        *self.0.borrow_mut() = state;
+        Ok(None)
+    }
+
+    /// Values realized so far, and whether that is all of them
+    pub fn realized(&self) -> (Vec<Member<'a>>, bool) {
+        let mut values = Vec::new();
+        let mut state = self.0.borrow().clone();
+        loop {
+            match state {
+                State::Cons(first, rest) => {
+                    values.push(first);
+                    state = match rest {
+                        Member::Seq(seq) => seq.0.borrow().clone(),
+                        Member::List(l) => {
+                            values.extend(l.iter().cloned());
+                            State::Empty
+                        }
+                        _ => State::Empty,
+                    };
+                }
+                State::Empty => return (values, true),
+                State::Unrealized(_) | State::Realizing(_) => return (values, false),
+            }
+        }
+    }
+}
+
+impl Drop for Seq<'_> {
+    // realized sequences are chains of cells as long as lists, and overflow the stack the same way
+    fn drop(&mut self) {
+        let mut next = take_rest(self.0.get_mut());
+        while let Some(seq) = next {
+            match Rc::try_unwrap(seq) {
+                Ok(mut seq) => next = take_rest(seq.0.get_mut()),
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+fn take_rest<'a>(state: &mut State<'a>) -> Option<Rc<Seq<'a>>> {
+    match state {
+        State::Cons(_, rest @ Member::Seq(_)) => match mem::replace(rest, Member::Unit) {
+            Member::Seq(seq) => Some(seq),
+            _ => unreachable!(),
+        },
+        _ => None,
+    }
+}
+
+impl PartialEq for Seq<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Debug for Seq<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let (values, complete) = self.realized();
+        let mut list = f.debug_list();
+        list.entries(values.iter());
+        if !complete {
+            list.entry(&format_args!(".."));
+        }
+        list.finish()
+    }
+}
@@ -56,7 +56,6 @@
 //         panic!("Variable with name {ident} not found");
 //     }
 
-
 //     fn update_var(&mut self, ident: &'a str, value: SexprResult<'a>) {
 //         let last = self.vars.len() - 1;
 //         assert!(last > 0);
@@ -168,7 +167,7 @@
 //                         }
 //                     }
 //                 }
-//             } 
+//             }
 //         }
 //     }
 // }
@@ -0,0 +1,608 @@
+use crate::{
+    ast::Member,
+    error::RispError,
+    interp::{Arity, Builtin},
+};
+
+type OpResult<'a> = Result<Member<'a>, RispError>;
+
+fn expect_string<'a>(name: &str, arg: Option<Member<'a>>) -> Result<String, RispError> {
+    match arg {
+        Some(Member::String(s)) => Ok(s),
+        Some(other) => Err(RispError::Type(format!(
+            "{name} expects a string, got {other}"
+        ))),
+        None => Err(RispError::Arity(format!("{name} expects a string"))),
+    }
+}
+
+/// String searched for or replaced, a char may stand for a string of its own
+fn expect_pattern<'a>(name: &str, arg: Option<Member<'a>>) -> Result<String, RispError> {
+    match arg {
+        Some(Member::Char(c)) => Ok(c.to_string()),
+        arg => expect_string(name, arg),
+    }
+}
+
+fn single_string<'a>(name: &str, args: Vec<Member<'a>>) -> Result<String, RispError> {
+    match <[Member<'a>; 1]>::try_from(args) {
+        Ok([arg]) => expect_string(name, Some(arg)),
+        Err(_) => Err(RispError::Arity(format!("{name} expects 1 argument"))),
+    }
+}
+
+fn single_char<'a>(name: &str, args: Vec<Member<'a>>) -> Result<char, RispError> {
+    match <[Member<'a>; 1]>::try_from(args) {
+        Ok([Member::Char(c)]) => Ok(c),
+        Ok([other]) => Err(RispError::Type(format!(
+            "{name} expects a char, got {other}"
+        ))),
+        Err(_) => Err(RispError::Arity(format!("{name} expects 1 argument"))),
+    }
+}
+
+fn is<'a>(args: Vec<Member<'a>>, test: impl Fn(&Member<'a>) -> bool) -> OpResult<'a> {
+    match args.as_slice() {
+        [value] => Ok(Member::Bool(test(value))),
+        _ => Err(RispError::Arity("Type predicates expect 1 argument".into())),
+    }
+}
+
+/// Char index or count given as an argument
+fn expect_count<'a>(name: &str, arg: Member<'a>) -> Result<usize, RispError> {
+    let n = arg.into_inum_value()?;
+    usize::try_from(n).map_err(|_| RispError::Type(format!("{name} got a negative index {n}")))
+}
+
+/// Byte offset of the char at `index`, the end of the string counting as one past the last char
+fn byte_offset(s: &str, index: usize) -> Option<usize> {
+    s.char_indices()
+        .map(|(offset, _)| offset)
+        .chain([s.len()])
+        .nth(index)
+}
+
+/// `(string-length s)` in chars, `(string-length s :graphemes)` in user-perceived
+/// characters or `(string-length s :bytes)` in UTF-8 bytes
+fn fun_std_string_length<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    let mut args = args.into_iter();
+    let s = expect_string("string-length", args.next())?;
+    let len = match (args.next(), args.next()) {
+        (None | Some(Member::Keyword("chars")), None) => s.chars().count(),
+        (Some(Member::Keyword("graphemes")), None) => graphemes(&s).len(),
+        (Some(Member::Keyword("bytes")), None) => s.len(),
+        _ => {
+            return Err(RispError::Arity(
+                "string-length expects a string and one of :chars, :graphemes or :bytes".into(),
+            ))
+        }
+    };
+    Ok(Member::NumI(len as i32))
+}
+
+/// `(substring s start)` or `(substring s start end)`
+fn fun_std_substring<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    let mut args = args.into_iter();
+    let s = expect_string("substring", args.next())?;
+    let (Some(start), end, None) = (args.next(), args.next(), args.next()) else {
+        return Err(RispError::Arity(
+            "substring expects a string, a start and an optional end".into(),
+        ));
+    };
+    let start = expect_count("substring", start)?;
+    let end = match end {
+        Some(end) => expect_count("substring", end)?,
+        None => s.chars().count(),
+    };
+    match (byte_offset(&s, start), byte_offset(&s, end)) {
+        (Some(from), Some(to)) if start <= end => Ok(Member::String(s[from..to].to_string())),
+        _ => Err(RispError::Type(format!(
+            "substring {start}..{end} is out of range for a string of {} chars",
+            s.chars().count()
+        ))),
+    }
+}
+
+/// `(string-ref s index)`, the char at `index`
+fn fun_std_string_ref<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    let mut args = args.into_iter();
+    let s = expect_string("string-ref", args.next())?;
+    let (Some(index), None) = (args.next(), args.next()) else {
+        return Err(RispError::Arity(
+            "string-ref expects a string and an index".into(),
+        ));
+    };
+    let index = expect_count("string-ref", index)?;
+    s.chars().nth(index).map(Member::Char).ok_or_else(|| {
+        RispError::Type(format!(
+            "Index {index} is out of range for a string of {} chars",
+            s.chars().count()
+        ))
+    })
+}
+
+/// `(split s separator)`, or `(split s)` to split on whitespace
+fn fun_std_split<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    let mut args = args.into_iter();
+    let s = expect_string("split", args.next())?;
+    let parts: Vec<&str> = match (args.next(), args.next()) {
+        (None, None) => s.split_whitespace().collect(),
+        (separator @ Some(_), None) => {
+            let separator = expect_pattern("split", separator)?;
+            if separator.is_empty() {
+                return Err(RispError::Type(
+                    "split needs a separator that isn't empty".into(),
+                ));
+            }
+            s.split(separator.as_str()).collect()
+        }
+        _ => {
+            return Err(RispError::Arity(
+                "split expects a string and an optional separator".into(),
+            ))
+        }
+    };
+    Ok(Member::List(
+        parts
+            .into_iter()
+            .map(|part| Member::String(part.to_string()))
+            .collect(),
+    ))
+}
+
+/// `(join values)` or `(join values separator)`, values that aren't strings are
+/// written the way `+` adds them to a string
+fn fun_std_join<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    let mut args = args.into_iter();
+    let values: Vec<Member> = match args.next() {
+        Some(Member::List(l)) => l.iter().cloned().collect(),
+        Some(Member::Vector(v)) => v.iter().cloned().collect(),
+        Some(Member::Unit) => Vec::new(),
+        Some(other) => {
+            return Err(RispError::Type(format!(
+                "join expects a list or a vector, got {other}"
+            )))
+        }
+        None => return Err(RispError::Arity("join expects a list or a vector".into())),
+    };
+    let separator = match (args.next(), args.next()) {
+        (None, None) => String::new(),
+        (separator @ Some(_), None) => expect_pattern("join", separator)?,
+        _ => {
+            return Err(RispError::Arity(
+                "join expects values and an optional separator".into(),
+            ))
+        }
+    };
+    let parts = values
+        .into_iter()
+        .map(Member::into_string_value)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Member::String(parts.join(&separator)))
+}
+
+fn fun_std_trim<'a>(name: &str, args: Vec<Member<'a>>, trim: fn(&str) -> &str) -> OpResult<'a> {
+    let s = single_string(name, args)?;
+    Ok(Member::String(trim(&s).to_string()))
+}
+
+/// `upcase` and `downcase` of a string or a char. Chars whose case maps to several,
+/// like `ß` to `SS`, give a string
+fn fun_std_case<'a>(
+    name: &str,
+    args: Vec<Member<'a>>,
+    convert: fn(&str) -> String,
+) -> OpResult<'a> {
+    match <[Member<'a>; 1]>::try_from(args) {
+        Ok([Member::String(s)]) => Ok(Member::String(convert(&s))),
+        Ok([Member::Char(c)]) => {
+            let mapped = convert(&c.to_string());
+            let mut chars = mapped.chars();
+            Ok(match (chars.next(), chars.next()) {
+                (Some(c), None) => Member::Char(c),
+                _ => Member::String(mapped),
+            })
+        }
+        Ok([other]) => Err(RispError::Type(format!(
+            "{name} expects a string or a char, got {other}"
+        ))),
+        Err(_) => Err(RispError::Arity(format!("{name} expects 1 argument"))),
+    }
+}
+
+/// `(starts-with? s prefix)` and `(ends-with? s suffix)`
+fn fun_std_affix<'a>(
+    name: &str,
+    args: Vec<Member<'a>>,
+    test: fn(&str, &str) -> bool,
+) -> OpResult<'a> {
+    let mut args = args.into_iter();
+    let s = expect_string(name, args.next())?;
+    let affix = expect_pattern(name, args.next())?;
+    if args.next().is_some() {
+        return Err(RispError::Arity(format!("{name} expects 2 arguments")));
+    }
+    Ok(Member::Bool(test(&s, &affix)))
+}
+
+/// `(index-of s needle)` or `(index-of s needle from)`, the char index
+/// of the first occurrence at or after `from`, `()` if there is none
+fn fun_std_index_of<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    let mut args = args.into_iter();
+    let s = expect_string("index-of", args.next())?;
+    let needle = expect_pattern("index-of", args.next())?;
+    let from = match (args.next(), args.next()) {
+        (None, None) => 0,
+        (Some(from), None) => expect_count("index-of", from)?,
+        _ => {
+            return Err(RispError::Arity(
+                "index-of expects a string, what to find and an optional start".into(),
+            ))
+        }
+    };
+    let Some(start) = byte_offset(&s, from) else {
+        return Ok(Member::Unit);
+    };
+    Ok(match s[start..].find(&needle) {
+        Some(found) => Member::NumI((from + s[start..start + found].chars().count()) as i32),
+        None => Member::Unit,
+    })
+}
+
+/// `(replace s from to)` replaces every occurrence of `from`
+fn fun_std_replace<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    let mut args = args.into_iter();
+    let s = expect_string("replace", args.next())?;
+    let from = expect_pattern("replace", args.next())?;
+    let to = expect_pattern("replace", args.next())?;
+    if args.next().is_some() {
+        return Err(RispError::Arity("replace expects 3 arguments".into()));
+    }
+    if from.is_empty() {
+        return Err(RispError::Type("replace needs something to replace".into()));
+    }
+    Ok(Member::String(s.replace(&from, &to)))
+}
+
+/// Radix given as an optional last argument, 10 without one
+fn expect_radix<'a>(name: &str, arg: Option<Member<'a>>) -> Result<u32, RispError> {
+    let radix = match arg {
+        Some(radix) => radix.into_inum_value()?,
+        None => 10,
+    };
+    match radix {
+        2..=36 => Ok(radix as u32),
+        _ => Err(RispError::Type(format!(
+            "{name} expects a radix between 2 and 36, got {radix}"
+        ))),
+    }
+}
+
+/// `(string->number s)` or `(string->number s radix)`, `()` if `s` isn't a number
+fn fun_std_string_to_number<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    let mut args = args.into_iter();
+    let s = expect_string("string->number", args.next())?;
+    let radix = expect_radix("string->number", args.next())?;
+    Ok(match i32::from_str_radix(&s, radix) {
+        Ok(n) => Member::NumI(n),
+        Err(_) => Member::Unit,
+    })
+}
+
+/// `(number->string n)` or `(number->string n radix)`, digits above 9 are lowercase letters
+fn fun_std_number_to_string<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    let mut args = args.into_iter();
+    let n = args
+        .next()
+        .ok_or_else(|| RispError::Arity("number->string expects a number".into()))?
+        .into_inum_value()?;
+    let radix = expect_radix("number->string", args.next())?;
+    Ok(Member::String(to_radix(n, radix)))
+}
+
+pub fn to_radix(n: i32, radix: u32) -> String {
+    let mut magnitude = n.unsigned_abs();
+    let mut digits = Vec::new();
+    loop {
+        digits.push(char::from_digit(magnitude % radix, radix).unwrap());
+        magnitude /= radix;
+        if magnitude == 0 {
+            break;
+        }
+    }
+    if n < 0 {
+        digits.push('-');
+    }
+    digits.iter().rev().collect()
+}
+
+/// `(string value ...)` puts chars and strings together, other values are written
+/// the way `+` adds them to a string
+fn fun_std_string<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    Ok(Member::String(
+        args.into_iter()
+            .map(Member::into_string_value)
+            .collect::<Result<_, _>>()?,
+    ))
+}
+
+fn fun_std_list_to_string<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    let values: Vec<Member> = match <[Member<'a>; 1]>::try_from(args) {
+        Ok([Member::List(l)]) => l.iter().cloned().collect(),
+        Ok([Member::Unit]) => Vec::new(),
+        Ok([other]) => {
+            return Err(RispError::Type(format!(
+                "list->string expects a list of chars, got {other}"
+            )))
+        }
+        Err(_) => return Err(RispError::Arity("list->string expects 1 argument".into())),
+    };
+    values
+        .into_iter()
+        .map(|value| match value {
+            Member::Char(c) => Ok(c),
+            other => Err(RispError::Type(format!(
+                "list->string expects a list of chars, got {other} in it"
+            ))),
+        })
+        .collect::<Result<String, _>>()
+        .map(Member::String)
+}
+
+fn fun_std_integer_to_char<'a>(args: Vec<Member<'a>>) -> OpResult<'a> {
+    let [n] = <[Member<'a>; 1]>::try_from(args)
+        .map_err(|_| RispError::Arity("integer->char expects 1 argument".into()))?;
+    let n = n.into_inum_value()?;
+    u32::try_from(n)
+        .ok()
+        .and_then(char::from_u32)
+        .map(Member::Char)
+        .ok_or_else(|| RispError::Type(format!("{n} is not the code point of a char")))
+}
+
+/// Splits into user-perceived characters. Approximates extended grapheme clusters:
+/// a char keeps the combining marks, variation selectors, emoji modifiers and zero
+/// width joined chars that follow it, regional indicators pair into flags and
+/// `\r\n` stays together
+pub fn graphemes(s: &str) -> Vec<&str> {
+    let mut clusters = Vec::new();
+    let mut start = 0;
+    let mut prev: Option<char> = None;
+    // regional indicators in the current cluster, a flag is made of two
+    let mut regional = 0;
+    for (i, c) in s.char_indices() {
+        let joined = match prev {
+            None => false,
+            Some(prev) => {
+                (prev == '\r' && c == '\n')
+                    || extends(c)
+                    || prev == '\u{200D}'
+                    || (is_regional(c) && regional % 2 == 1)
+            }
+        };
+        if !joined && i > start {
+            clusters.push(&s[start..i]);
+            start = i;
+            regional = 0;
+        }
+        if is_regional(c) {
+            regional += 1;
+        }
+        prev = Some(c);
+    }
+    if start < s.len() {
+        clusters.push(&s[start..]);
+    }
+    clusters
+}
+
+/// Chars that attach to the one before them
+fn extends(c: char) -> bool {
+    matches!(c,
+        '\u{0300}'..='\u{036F}'
+        | '\u{0483}'..='\u{0489}'
+        | '\u{0591}'..='\u{05BD}'
+        | '\u{0610}'..='\u{061A}'
+        | '\u{064B}'..='\u{065F}'
+        | '\u{0670}'
+        | '\u{06D6}'..='\u{06DC}'
+        | '\u{0900}'..='\u{0903}'
+        | '\u{093A}'..='\u{094F}'
+        | '\u{0E31}'
+        | '\u{0E34}'..='\u{0E3A}'
+        | '\u{0E47}'..='\u{0E4E}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{200C}'..='\u{200D}'
+        | '\u{20D0}'..='\u{20FF}'
+        | '\u{FE00}'..='\u{FE0F}'
+        | '\u{FE20}'..='\u{FE2F}'
+        | '\u{1F3FB}'..='\u{1F3FF}'
+        | '\u{E0020}'..='\u{E007F}'
+        | '\u{E0100}'..='\u{E01EF}'
+    )
+}
+
+fn is_regional(c: char) -> bool {
+    matches!(c, '\u{1F1E6}'..='\u{1F1FF}')
+}
+
+pub fn builtins<'a>() -> Vec<Builtin<'a>> {
+    use Arity::{AtLeast, Between, Exactly};
+    vec![
+        Builtin::value(
+            "string-length",
+            Between(1, 2),
+            "(string-length s [:chars|:graphemes|:bytes]) length of a string, in chars by default",
+            |_, args, _| fun_std_string_length(args),
+        ),
+        Builtin::value(
+            "substring",
+            Between(2, 3),
+            "(substring s start [end]) chars of s from start up to end",
+            |_, args, _| fun_std_substring(args),
+        ),
+        Builtin::value(
+            "string-ref",
+            Exactly(2),
+            "(string-ref s index) char at the index",
+            |_, args, _| fun_std_string_ref(args),
+        ),
+        Builtin::value(
+            "split",
+            Between(1, 2),
+            "(split s [separator]) list of the parts of s, split on whitespace by default",
+            |_, args, _| fun_std_split(args),
+        ),
+        Builtin::value(
+            "join",
+            Between(1, 2),
+            "(join values [separator]) values of a list or vector displayed one after the other",
+            |_, args, _| fun_std_join(args),
+        ),
+        Builtin::value(
+            "trim",
+            Exactly(1),
+            "(trim s) s without whitespace at either end",
+            |name, args, _| fun_std_trim(name, args, str::trim),
+        ),
+        Builtin::value(
+            "trim-start",
+            Exactly(1),
+            "(trim-start s) s without whitespace at the start",
+            |name, args, _| fun_std_trim(name, args, str::trim_start),
+        ),
+        Builtin::value(
+            "trim-end",
+            Exactly(1),
+            "(trim-end s) s without whitespace at the end",
+            |name, args, _| fun_std_trim(name, args, str::trim_end),
+        ),
+        Builtin::value(
+            "upcase",
+            Exactly(1),
+            "(upcase s) s in uppercase",
+            |name, args, _| fun_std_case(name, args, str::to_uppercase),
+        ),
+        Builtin::value(
+            "downcase",
+            Exactly(1),
+            "(downcase s) s in lowercase",
+            |name, args, _| fun_std_case(name, args, str::to_lowercase),
+        ),
+        Builtin::value(
+            "starts-with?",
+            Exactly(2),
+            "(starts-with? s prefix) whether s starts with prefix",
+            |name, args, _| fun_std_affix(name, args, |s, prefix| s.starts_with(prefix)),
+        ),
+        Builtin::value(
+            "ends-with?",
+            Exactly(2),
+            "(ends-with? s suffix) whether s ends with suffix",
+            |name, args, _| fun_std_affix(name, args, |s, suffix| s.ends_with(suffix)),
+        ),
+        Builtin::value(
+            "index-of",
+            Between(2, 3),
+            "(index-of s what [start]) index of the first what in s from start, () if it isn't there",
+            |_, args, _| fun_std_index_of(args),
+        ),
+        Builtin::value(
+            "replace",
+            Exactly(3),
+            "(replace s from to) s with every from replaced by to",
+            |_, args, _| fun_std_replace(args),
+        ),
+        Builtin::value(
+            "string->number",
+            Between(1, 2),
+            "(string->number s [radix]) number written in s, () if it isn't one",
+            |_, args, _| fun_std_string_to_number(args),
+        ),
+        Builtin::value(
+            "number->string",
+            Between(1, 2),
+            "(number->string n [radix]) digits of a number",
+            |_, args, _| fun_std_number_to_string(args),
+        ),
+        Builtin::value(
+            "string",
+            AtLeast(0),
+            "(string values...) values displayed one after the other",
+            |_, args, _| fun_std_string(args),
+        ),
+        Builtin::value(
+            "string->list",
+            Exactly(1),
+            "(string->list s) list of the chars of s",
+            |name, args, _| {
+                single_string(name, args)
+                    .map(|s| Member::List(s.chars().map(Member::Char).collect()))
+            },
+        ),
+        Builtin::value(
+            "list->string",
+            Exactly(1),
+            "(list->string chars) string of a list of chars",
+            |_, args, _| fun_std_list_to_string(args),
+        ),
+        Builtin::value(
+            "graphemes",
+            Exactly(1),
+            "(graphemes s) list of the user-perceived characters of s",
+            |name, args, _| {
+                single_string(name, args).map(|s| {
+                    Member::List(
+                        graphemes(&s)
+                            .into_iter()
+                            .map(|g| Member::String(g.to_string()))
+                            .collect(),
+                    )
+                })
+            },
+        ),
+        Builtin::value(
+            "string?",
+            Exactly(1),
+            "(string? value) whether the value is a string",
+            |_, args, _| is(args, |value| matches!(value, Member::String(_))),
+        ),
+        Builtin::value(
+            "char?",
+            Exactly(1),
+            "(char? value) whether the value is a char",
+            |_, args, _| is(args, |value| matches!(value, Member::Char(_))),
+        ),
+        Builtin::value(
+            "char-alphabetic?",
+            Exactly(1),
+            "(char-alphabetic? c) whether c is a letter",
+            |name, args, _| single_char(name, args).map(|c| Member::Bool(c.is_alphabetic())),
+        ),
+        Builtin::value(
+            "char-numeric?",
+            Exactly(1),
+            "(char-numeric? c) whether c is a digit",
+            |name, args, _| single_char(name, args).map(|c| Member::Bool(c.is_numeric())),
+        ),
+        Builtin::value(
+            "char-whitespace?",
+            Exactly(1),
+            "(char-whitespace? c) whether c is whitespace",
+            |name, args, _| single_char(name, args).map(|c| Member::Bool(c.is_whitespace())),
+        ),
+        Builtin::value(
+            "char->integer",
+            Exactly(1),
+            "(char->integer c) code point of c",
+            |name, args, _| single_char(name, args).map(|c| Member::NumI(c as i32)),
+        ),
+        Builtin::value(
+            "integer->char",
+            Exactly(1),
+            "(integer->char n) char of a code point",
+            |_, args, _| fun_std_integer_to_char(args),
+        ),
+    ]
+}
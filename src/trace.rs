@@ -0,0 +1,63 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+use crate::ast::{Ident, Member, SExpr};
+
+/// How much of the evaluation is traced, each level traces what the ones below it do
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    #[default]
+    Off,
+    /// Top level forms and their values
+    Forms,
+    /// Every function and builtin applied, with its arguments
+    Calls,
+}
+
+/// Something the interpreter did, handed to the tracer if its level is traced
+#[derive(Debug)]
+pub enum Event<'e, 'a> {
+    /// Top level form about to be evaluated
+    Form(&'e SExpr<'a>),
+    /// Value of the top level form evaluated last
+    Value(&'e Member<'a>),
+    Call {
+        fun: Ident<'a>,
+        args: &'e [Member<'a>],
+        /// Nested calls the function is applied in
+        depth: usize,
+    },
+}
+
+impl Event<'_, '_> {
+    pub fn level(&self) -> Level {
+        match self {
+            Self::Form(_) | Self::Value(_) => Level::Forms,
+            Self::Call { .. } => Level::Calls,
+        }
+    }
+}
+
+impl Display for Event<'_, '_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Form(form) => write!(f, "form {form}"),
+            Self::Value(value) => write!(f, "value {value}"),
+            Self::Call { fun, args, depth } => {
+                write!(f, "{:width$}call ({fun}", "", width = depth * 2)?;
+                for arg in args.iter() {
+                    write!(f, " {arg}")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// Receives the events traced while a program runs
+pub type Tracer<'a> = Box<dyn FnMut(&Event<'_, 'a>) + 'a>;
+
+/// Writes each event on its own line of stderr, so it doesn't mix with the program's output
+pub fn stderr_tracer<'a>() -> Tracer<'a> {
+    Box::new(|event| eprintln!("[trace] {event}"))
+}
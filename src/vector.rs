@@ -0,0 +1,226 @@
+use core::fmt;
+use std::{fmt::Formatter, mem, rc::Rc};
+
+use crate::ast::{drop_values, Member};
+
+const BITS: usize = 5;
+const WIDTH: usize = 1 << BITS;
+const MASK: usize = WIDTH - 1;
+
+/// Immutable vector stored as a trie of 32 wide nodes, with the last values kept apart
+/// in a tail so that pushing rarely touches the trie. Updated vectors share every node
+/// but the ones on the path to what changed
+#[derive(Clone)]
+pub struct Vector<'a> {
+    len: usize,
+    /// Bits of an index above the ones picking a value in a leaf
+    shift: usize,
+    root: Rc<Node<'a>>,
+    tail: Rc<Vec<Member<'a>>>,
+}
+
+enum Node<'a> {
+    Branch(Vec<Rc<Node<'a>>>),
+    Leaf(Vec<Member<'a>>),
+}
+
+impl<'a> Node<'a> {
+    fn children(&self) -> &[Rc<Node<'a>>] {
+        match self {
+            Self::Branch(children) => children,
+            Self::Leaf(_) => unreachable!("leaves are only found at the bottom of the trie"),
+        }
+    }
+
+    /// Branches leading down to `node` through `level` more levels
+    fn path(level: usize, node: Rc<Self>) -> Rc<Self> {
+        match level {
+            0 => node,
+            _ => Rc::new(Self::Branch(vec![Self::path(level - BITS, node)])),
+        }
+    }
+}
+
+impl<'a> Vector<'a> {
+    pub fn new() -> Self {
+        Self {
+            len: 0,
+            shift: BITS,
+            root: Rc::new(Node::Branch(Vec::new())),
+            tail: Rc::new(Vec::new()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Index of the first value held in the tail
+    fn tail_offset(&self) -> usize {
+        match self.len {
+            0..WIDTH => 0,
+            len => ((len - 1) >> BITS) << BITS,
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Member<'a>> {
+        if index >= self.len {
+            return None;
+        }
+        if index >= self.tail_offset() {
+            return self.tail.get(index - self.tail_offset());
+        }
+        let mut node = &self.root;
+        let mut level = self.shift;
+        while level > 0 {
+            node = &node.children()[(index >> level) & MASK];
+            level -= BITS;
+        }
+        match &**node {
+            Node::Leaf(values) => values.get(index & MASK),
+            Node::Branch(_) => unreachable!(),
+        }
+    }
+
+    pub fn push(&self, value: Member<'a>) -> Self {
+        if self.len - self.tail_offset() < WIDTH {
+            let mut tail = (*self.tail).clone();
+            tail.push(value);
+            return Self {
+                len: self.len + 1,
+                shift: self.shift,
+                root: self.root.clone(),
+                tail: Rc::new(tail),
+            };
+        }
+        // the tail is full, it goes into the trie and a new one is started
+        let leaf = Rc::new(Node::Leaf((*self.tail).clone()));
+        let (root, shift) = if (self.len >> BITS) > (1 << self.shift) {
+            let root = Node::Branch(vec![self.root.clone(), Node::path(self.shift, leaf)]);
+            (Rc::new(root), self.shift + BITS)
+        } else {
+            (self.push_leaf(self.shift, &self.root, leaf), self.shift)
+        };
+        Self {
+            len: self.len + 1,
+            shift,
+            root,
+            tail: Rc::new(vec![value]),
+        }
+    }
+
+    fn push_leaf(&self, level: usize, parent: &Node<'a>, leaf: Rc<Node<'a>>) -> Rc<Node<'a>> {
+        let mut children = parent.children().to_vec();
+        let index = ((self.len - 1) >> level) & MASK;
+        let child = match level {
+            BITS => leaf,
+            _ => match children.get(index) {
+                Some(child) => self.push_leaf(level - BITS, child, leaf),
+                None => Node::path(level - BITS, leaf),
+            },
+        };
+        match index < children.len() {
+            true => children[index] = child,
+            false => children.push(child),
+        }
+        Rc::new(Node::Branch(children))
+    }
+
+    /// Copy of the vector with the value at `index` replaced, `None` if it's out of bounds
+    pub fn set(&self, index: usize, value: Member<'a>) -> Option<Self> {
+        if index >= self.len {
+            return None;
+        }
+        if index >= self.tail_offset() {
+            let mut tail = (*self.tail).clone();
+            tail[index - self.tail_offset()] = value;
+            return Some(Self {
+                len: self.len,
+                shift: self.shift,
+                root: self.root.clone(),
+                tail: Rc::new(tail),
+            });
+        }
+        Some(Self {
+            len: self.len,
+            shift: self.shift,
+            root: Self::set_in(self.shift, &self.root, index, value),
+            tail: self.tail.clone(),
+        })
+    }
+
+    fn set_in(level: usize, node: &Node<'a>, index: usize, value: Member<'a>) -> Rc<Node<'a>> {
+        Rc::new(match node {
+            Node::Leaf(values) => {
+                let mut values = values.clone();
+                values[index & MASK] = value;
+                Node::Leaf(values)
+            }
+            Node::Branch(children) => {
+                let mut children = children.clone();
+                let child = (index >> level) & MASK;
+                children[child] = Self::set_in(level - BITS, &children[child], index, value);
+                Node::Branch(children)
+            }
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Member<'a>> {
+        (0..self.len).map(|i| self.get(i).unwrap())
+    }
+
+    /// Moves the values only this vector holds into `values`, see [`drop_values`]
+    pub fn take_values(&mut self, values: &mut Vec<Member<'a>>) {
+        if let Some(tail) = Rc::get_mut(&mut self.tail) {
+            values.append(tail);
+        }
+        let mut nodes = match Rc::get_mut(&mut self.root) {
+            Some(Node::Branch(children)) => mem::take(children),
+            Some(Node::Leaf(leaf)) => return values.append(leaf),
+            None => return,
+        };
+        while let Some(node) = nodes.pop() {
+            match Rc::try_unwrap(node) {
+                Ok(Node::Branch(children)) => nodes.extend(children),
+                Ok(Node::Leaf(mut leaf)) => values.append(&mut leaf),
+                Err(_) => (),
+            }
+        }
+    }
+}
+
+impl Drop for Vector<'_> {
+    // values nested in values would otherwise be dropped recursively
+    fn drop(&mut self) {
+        let mut values = Vec::new();
+        self.take_values(&mut values);
+        drop_values(values);
+    }
+}
+
+impl Default for Vector<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> FromIterator<Member<'a>> for Vector<'a> {
+    fn from_iter<T: IntoIterator<Item = Member<'a>>>(iter: T) -> Self {
+        iter.into_iter()
+            .fold(Self::new(), |vector, value| vector.push(value))
+    }
+}
+
+impl PartialEq for Vector<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for Vector<'_> {}
+
+impl fmt::Debug for Vector<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
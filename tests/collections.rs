@@ -0,0 +1,77 @@
+use language::{ErrorKind, Interpreter, Member};
+
+fn eval(source: &str) -> Result<Member<'static>, language::Error> {
+    Interpreter::new().eval_str(source)
+}
+
+#[test]
+fn maps_are_read_updated_and_merged_without_changing() {
+    let value = eval(
+        "(let m {:a 1 :b 2})
+         (list (get m :a) (get m :z) (get m :z 0)
+               (assoc m :c 3) (dissoc m :a) (merge m {:b 20 :d 4})
+               (contains? m :a) m)",
+    );
+    assert_eq!(
+        value,
+        eval("(list 1 () 0 {:a 1 :b 2 :c 3} {:b 2} {:a 1 :b 20 :d 4} true {:a 1 :b 2})")
+    );
+    // in no particular order
+    let value =
+        eval("(list (apply hash-set (keys {:a 1 :b 2})) (apply hash-set (vals {:a 1 :b 2})))");
+    assert_eq!(value, eval("(list #{:a :b} #{1 2})"));
+}
+
+#[test]
+fn sets_hold_each_value_once() {
+    let value = eval(
+        "(list #{1 1 2} (union #{1 2} #{2 3}) (intersection #{1 2 3} #{2 3 4})
+               (contains? #{1 2} 3))",
+    );
+    assert_eq!(value, eval("(list #{1 2} #{1 2 3} #{2 3} false)"));
+}
+
+#[test]
+fn collections_are_equal_and_found_by_value() {
+    assert_eq!(eval("(= {:a 1 :b 2} {:b 2 :a 1})"), Ok(Member::Bool(true)));
+    assert_eq!(eval("(= {:a 1} {:a 2})"), Ok(Member::Bool(false)));
+    assert_eq!(
+        eval("(= #{(list 1 2) [3]} #{[3] (list 1 2)})"),
+        Ok(Member::Bool(true))
+    );
+    let value = eval(
+        "(list (get {{:k 1} :map} {:k 1}) (get {(list 1 2) :list} (list 1 2))
+               (get {[1 2] :vector} (list 1 2)) (get {1 :one} \"1\"))",
+    );
+    assert_eq!(value, eval("(list :map :list () ())"));
+}
+
+#[test]
+fn map_literals_need_a_value_for_every_key() {
+    let error = eval("(list {:a 1 :b})").unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::Parse);
+    let error = eval("(hash-map :a 1 :b)").unwrap_err();
+    assert!(
+        error.to_string().contains("expects a value for every key"),
+        "{error}"
+    );
+}
+
+#[test]
+fn vectors_are_indexed_and_updated_without_changing() {
+    let value = eval(
+        "(let v (list->vector (to-list (take 2000 (range)))))
+         (let w (vector-set v 1500 :x))
+         (list (vector-ref v 1500) (vector-ref w 1500) (length w)
+               (push [1 2] 3) (slice [1 2 3 4] 1 3) (vector->list [1 2]) (conj [1] 2))",
+    );
+    assert_eq!(
+        value,
+        eval("(list 1500 :x 2000 (vector 1 2 3) (vector 2 3) (list 1 2) (vector 1 2))")
+    );
+    let error = eval("(vector-ref [1 2] 5)").unwrap_err();
+    assert!(
+        error.to_string().contains("Index 5 is out of bounds"),
+        "{error}"
+    );
+}
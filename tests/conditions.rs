@@ -0,0 +1,53 @@
+use language::{ErrorKind, Interpreter, Member, ProgContext};
+
+fn eval(source: &str) -> Result<Member<'static>, language::Error> {
+    Interpreter::new().eval_str(source)
+}
+
+#[test]
+fn handlers_invoke_the_restarts_of_the_code_that_signalled() {
+    let value = eval(
+        "(handler-bind ((:low (lambda (c) (invoke-restart :use 41))))
+           (restart-case (+ 1 (error :low \"low\")) (:use (v) v)))",
+    );
+    // the restart's value is what the whole `restart-case` returns
+    assert_eq!(value, Ok(Member::NumI(41)));
+    let value = eval("(restart-case (* 2 3) (:use (v) v))");
+    assert_eq!(value, Ok(Member::NumI(6)));
+}
+
+#[test]
+fn handlers_returning_decline_the_condition() {
+    let value = eval(
+        "(handler-bind ((_ (lambda (c) 0)))
+           (try (error :low \"x\") (catch :low e (error-message e))))",
+    );
+    assert_eq!(value, Ok(Member::String("x".into())));
+    let error = eval("(handler-bind ((:low (lambda (c) 0))) (error :low \"x\"))").unwrap_err();
+    assert_eq!(error.to_string(), "Uncaught :low x");
+}
+
+#[test]
+fn signals_and_warnings_carry_on_when_nothing_handles_them() {
+    assert_eq!(eval("(signal :note \"noted\")"), Ok(Member::Unit));
+    let mut errors = Vec::new();
+    let context = ProgContext::default().with_error_output(&mut errors);
+    let value = Interpreter::with_context(context).eval_str(
+        "(list
+           (handler-bind ((:w (lambda (c) (invoke-restart :muffle))))
+             (restart-case (warn :w \"muffled\") (:muffle () :muffled)))
+           (warn :w \"shown\"))",
+    );
+    assert_eq!(value.unwrap().to_string(), "(list :muffled ())");
+    assert_eq!(String::from_utf8(errors).unwrap(), "Warning: shown\n");
+}
+
+#[test]
+fn only_active_restarts_can_be_invoked() {
+    let error = eval("(invoke-restart :nope)").unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::Runtime);
+    assert!(
+        error.to_string().contains("No restart :nope is active"),
+        "{error}"
+    );
+}
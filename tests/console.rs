@@ -0,0 +1,30 @@
+use language::{Interpreter, ProgContext};
+
+/// What `source` writes when reading `input`
+fn run(source: &str, input: &str) -> String {
+    let mut output = Vec::new();
+    let context = ProgContext::default()
+        .with_output(&mut output)
+        .with_input(input.as_bytes());
+    Interpreter::with_context(context).eval_str(source).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn lines_are_read_without_their_line_break() {
+    let output = run(
+        "(println (read-line)) (println (read-line)) (write (read-line))",
+        "first\r\nsecond\n",
+    );
+    assert_eq!(output, "first\nsecond\n()");
+    assert_eq!(run("(write (read-all))", "a\nb"), "\"a\\nb\"");
+}
+
+#[test]
+fn print_separates_values_with_spaces() {
+    assert_eq!(
+        run("(print \"a\" 1 #\\c) (newline) (println (list \"x\"))", ""),
+        "a 1 c\n(x)\n"
+    );
+    assert_eq!(run("(pprint (list 1 2) 20)", ""), "(list 1 2)\n");
+}
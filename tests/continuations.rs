@@ -0,0 +1,71 @@
+use language::{ErrorKind, Interpreter, Member, ProgContext};
+
+fn eval(source: &str) -> Result<Member<'static>, language::Error> {
+    Interpreter::new().eval_str(source)
+}
+
+/// Value of `source` as written, along with the warnings it printed
+fn eval_warnings(source: &str) -> (String, String) {
+    let mut errors = Vec::new();
+    let context = ProgContext::default().with_error_output(&mut errors);
+    let value = Interpreter::with_context(context)
+        .eval_str(source)
+        .unwrap()
+        .to_string();
+    (value, String::from_utf8(errors).unwrap())
+}
+
+#[test]
+fn continuations_return_to_where_they_were_captured() {
+    assert_eq!(
+        eval("(+ 1 (call/cc (lambda (k) (+ 10 (k 5)))))"),
+        Ok(Member::NumI(6))
+    );
+    assert_eq!(
+        eval("(call-with-current-continuation (lambda (k) 3))"),
+        Ok(Member::NumI(3))
+    );
+}
+
+#[test]
+fn continuations_can_be_reentered_without_growing_the_stack() {
+    let value = eval(
+        "(def count-to (n)
+           (match (call/cc (lambda (k) (list 0 k)))
+             ((i k) (if (< i n) (k (list (+ i 1) k)) i))))
+         (count-to 120000)",
+    );
+    assert_eq!(value, Ok(Member::NumI(120000)));
+}
+
+#[test]
+fn dynamic_wind_runs_around_every_jump_in_and_out() {
+    let (value, warnings) = eval_warnings(
+        "(match (dynamic-wind (lambda () (warn \"in\"))
+                              (lambda () (call/cc (lambda (k) k)))
+                              (lambda () (warn \"out\")))
+           (k (if (= k 1) k (k 1))))",
+    );
+    assert_eq!(value, "1");
+    assert_eq!(
+        warnings,
+        "Warning: in\nWarning: out\nWarning: in\nWarning: out\n"
+    );
+    let (value, warnings) = eval_warnings(
+        "(call/ec (lambda (escape) (try (escape :left) (finally (warn \"cleaned up\")))))",
+    );
+    assert_eq!(value, ":left");
+    assert_eq!(warnings, "Warning: cleaned up\n");
+}
+
+#[test]
+fn escape_continuations_only_work_until_their_call_ec_returns() {
+    let error = eval("(let saved (call/ec (lambda (k) k))) (saved 1)").unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::Runtime);
+    assert!(
+        error
+            .to_string()
+            .contains("called after its call/ec returned"),
+        "{error}"
+    );
+}
@@ -0,0 +1,54 @@
+use language::{ErrorKind, Interpreter, Member};
+
+fn eval(source: &str) -> Result<Member<'static>, language::Error> {
+    Interpreter::new().eval_str(source)
+}
+
+#[test]
+fn handlers_resume_where_the_effect_was_performed() {
+    let value = eval("(handle (+ (perform :ask) (perform :ask)) (:ask () k (k 20)))");
+    assert_eq!(value, Ok(Member::NumI(40)));
+    // performed from functions called inside the handle, however deep
+    let value = eval(
+        "(def counter (n) (if (= n 0) (perform :get) (counter (- n 1))))
+         (handle (counter 5) (:get () k (k :state)))",
+    );
+    assert_eq!(value, Ok(Member::Keyword("state")));
+    // resuming gives the handler what the rest of the body evaluates to
+    let value = eval("(handle (list (perform :ask) (perform :ask)) (:ask () k (cons 0 (k 1))))");
+    assert_eq!(value, eval("(list 0 0 1 1)"));
+}
+
+#[test]
+fn handlers_not_resuming_abort_the_handled_body() {
+    let value = eval("(handle (+ 1 (perform :abort 5)) (:abort (v) k v))");
+    assert_eq!(value, Ok(Member::NumI(5)));
+}
+
+#[test]
+fn effects_go_to_the_innermost_handler_for_them() {
+    let value = eval(
+        "(handle (handle (+ (perform :a) (perform :b)) (:a () k (k 1)))
+           (:b () k (k 10)))",
+    );
+    assert_eq!(value, Ok(Member::NumI(11)));
+    // a handler's own effects are handled outside of it
+    let value =
+        eval("(handle (handle (perform :x) (:x () k (k (perform :y)))) (:y () k (k :outer)))");
+    assert_eq!(value, Ok(Member::Keyword("outer")));
+}
+
+#[test]
+fn unhandled_and_resumed_twice_are_errors() {
+    let error = eval("(perform :nothing)").unwrap_err();
+    assert!(
+        error.to_string().contains("No handler for effect :nothing"),
+        "{error}"
+    );
+    let error = eval("(handle (perform :ask) (:ask () k (do (k 1) (k 2))))").unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::Runtime);
+    assert!(
+        error.to_string().contains("only be resumed once"),
+        "{error}"
+    );
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use language::{Arity, Error, ErrorKind, FromRisp, Interpreter, IntoRisp, Member};
+
+/// `value` taken into Risp and back
+fn round_trip<'a, T: IntoRisp<'a> + FromRisp<'a>>(value: T) -> Result<T, Error> {
+    T::from_risp(value.into_risp())
+}
+
+#[test]
+fn rust_values_convert_to_risp_and_back() {
+    assert_eq!(round_trip(42), Ok(42));
+    assert_eq!(round_trip(true), Ok(true));
+    assert_eq!(round_trip('λ'), Ok('λ'));
+    assert_eq!(round_trip("text".to_string()), Ok("text".to_string()));
+    assert_eq!(round_trip(Some(3)), Ok(Some(3)));
+    assert_eq!(round_trip(None::<i32>), Ok(None));
+    assert_eq!(round_trip(vec![1, 2, 3]), Ok(vec![1, 2, 3]));
+    let map = HashMap::from([("a".to_string(), 1), ("b".to_string(), 2)]);
+    assert_eq!(round_trip(map.clone()), Ok(map));
+    let error = i32::from_risp(Member::String("1".into())).unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::Type);
+}
+
+#[test]
+fn host_functions_are_called_with_converted_arguments() {
+    let mut risp = Interpreter::new();
+    risp.define("add", "(add a b) sum of two numbers", |a: i32, b: i32| {
+        Ok::<_, Error>(a + b)
+    });
+    risp.define("shout", "(shout s) s in capitals", |s: String| {
+        Ok::<_, Error>(s.to_uppercase())
+    });
+    risp.define_variadic("count", Arity::AtLeast(0), "(count values...)", |args| {
+        Ok(Member::NumI(args.len() as i32))
+    });
+    assert_eq!(risp.eval_str("(add 2 (add 3 4))"), Ok(Member::NumI(9)));
+    assert_eq!(
+        risp.eval_str("(shout \"hi\")"),
+        Ok(Member::String("HI".into()))
+    );
+    assert_eq!(risp.eval_str("(count 1 2 3)"), Ok(Member::NumI(3)));
+    let error = risp.eval_str("(add 1 \"2\")").unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::Type);
+}
+
+#[test]
+fn host_functions_check_how_many_arguments_they_get() {
+    let mut risp = Interpreter::new();
+    risp.define("add", "(add a b)", |a: i32, b: i32| Ok::<_, Error>(a + b));
+    for source in ["(add 1)", "(add 1 2 3)"] {
+        let error = risp.eval_str(source).unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::Arity, "{source}");
+    }
+}
+
+#[test]
+fn errors_of_the_host_can_be_caught_by_their_kind() {
+    let mut risp = Interpreter::new();
+    risp.define("checked-div", "(checked-div a b)", |a: i32, b: i32| {
+        a.checked_div(b)
+            .ok_or_else(|| Error::new(ErrorKind::Thrown("div-by-zero".into()), "division by zero"))
+    });
+    let value = risp.eval_str("(try (checked-div 1 0) (catch :div-by-zero e (error-message e)))");
+    assert_eq!(value, Ok(Member::String("division by zero".into())));
+}
+
+#[test]
+fn variables_are_shared_between_the_host_and_the_program() {
+    let mut risp = Interpreter::new();
+    risp.set("names", vec!["ada", "grace"]);
+    risp.eval_str("(let count (length names))").unwrap();
+    assert_eq!(risp.get::<i32>("count"), Ok(2));
+    assert_eq!(
+        risp.get::<Vec<String>>("names"),
+        Ok(vec!["ada".to_string(), "grace".to_string()])
+    );
+    let error = risp.get::<i32>("missing").unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::Unbound);
+}
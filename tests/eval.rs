@@ -0,0 +1,454 @@
+use std::{cell::RefCell, rc::Rc};
+
+use language::{ErrorKind, FsAccess, Interpreter, Member, ProgContext, DEFAULT_MAX_DEPTH};
+
+fn eval(source: &str) -> Result<Member<'static>, language::Error> {
+    Interpreter::new().eval_str(source)
+}
+
+#[test]
+fn tail_calls_run_in_constant_space() {
+    let value = eval(
+        "(def count-down (n) (if (= n 0) \"done\" (count-down (- n 1))))
+         (count-down 1000000)",
+    );
+    assert_eq!(value, Ok(Member::String("done".into())));
+}
+
+#[test]
+fn mutual_tail_calls_run_in_constant_space() {
+    let value = eval(
+        "(def even (n) (if (= n 0) true (odd (- n 1))))
+         (def odd (n) (if (= n 0) false (even (- n 1))))
+         (even 100001)",
+    );
+    assert_eq!(value, Ok(Member::Bool(false)));
+}
+
+#[test]
+fn do_runs_its_forms_in_order_and_keeps_the_last_in_tail_position() {
+    let value = eval(
+        "(def count-down (n) (do (let m (- n 1)) (if (= n 0) \"done\" (count-down m))))
+         (count-down 120000)",
+    );
+    assert_eq!(value, Ok(Member::String("done".into())));
+    let value = eval("(to-list (generator (do (yield 1) (yield 2) (yield 3))))");
+    assert_eq!(value, eval("(list 1 2 3)"));
+}
+
+#[test]
+fn overflowing_arithmetic_is_an_error() {
+    for source in ["(- 0 -2147483648)", "(- -2147483648)", "(+ 2147483647 1)"] {
+        let error = eval(source).unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::Runtime, "{source}");
+    }
+    let caught = eval("(try (+ 2147483647 1) (catch :error e (error-message e)))");
+    assert_eq!(caught, Ok(Member::String("Integer overflow in +".into())));
+}
+
+#[test]
+fn deeply_nested_collections_are_dropped() {
+    for wrap in [
+        "(list v)",
+        "[v]",
+        "(hash-map :k v)",
+        "(hash-set v)",
+        "(list [v])",
+    ] {
+        let source = format!(
+            "(def nest (v n) (if (= n 0) v (nest {wrap} (- n 1))))
+             (let d (nest 1 20000))
+             (let e (nest 1 20000))
+             (if (= d e) (if (= d (nest 2 20000)) false (contains? (hash-set 1 d) e)) false)"
+        );
+        let value = Interpreter::new().eval_str(&source);
+        assert_eq!(value, Ok(Member::Bool(true)), "{wrap}");
+    }
+}
+
+#[test]
+fn misplaced_pattern_markers_are_rejected() {
+    for (source, message) in [
+        (
+            "(def f (&key a &optional b) a)",
+            "&optional can't come after &key",
+        ),
+        (
+            "(def f (a &rest &rest b) a)",
+            "&rest needs to be followed by a pattern",
+        ),
+        (
+            "(def f (a &rest) a)",
+            "&rest needs to be followed by a pattern",
+        ),
+        (
+            "(let [a &optional] [1])",
+            "&optional needs to be followed by a pattern",
+        ),
+        (
+            "(let (hash-map :a x :b) {:a 1})",
+            ":b needs to be followed by a pattern",
+        ),
+        (
+            "(let {:a x :or {y 1}} {:a 1})",
+            "y has a default but isn't bound by a key",
+        ),
+    ] {
+        let error = eval(source).unwrap_err();
+        assert_eq!(error.to_string(), message, "{source}");
+    }
+    let value = eval("(def f (a &optional (b 2) &rest r &key k) (list a b k)) (f 1 3 :k 4)");
+    assert_eq!(value, eval("(list 1 3 4)"));
+}
+
+#[test]
+fn map_patterns_fall_back_on_their_defaults() {
+    let value = eval(
+        "(def greet ({:name n :greeting g :or {g (string \"hel\" \"lo\")}}) (format \"{} {}\" g n))
+         (list (greet {:name \"ann\"}) (greet {:name \"bo\" :greeting \"hi\"}))",
+    );
+    assert_eq!(value, eval("(list \"hello ann\" \"hi bo\")"));
+    let error = eval("(let {:a x :b y :or {x 0}} {:a 1})").unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::Type);
+}
+
+#[test]
+fn match_bindings_stay_in_their_clause() {
+    let error = eval("(match 1 (x when false x) (_ x))").unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::Unbound);
+    let value = eval("(let x 10) (match 5 (x x)) (identity x)");
+    assert_eq!(value, Ok(Member::NumI(10)));
+    let value = eval(
+        "(def f (n) (match n (0 \"done\") (k (f (- k 1)))))
+         (f 200000)",
+    );
+    assert_eq!(value, Ok(Member::String("done".into())));
+}
+
+#[test]
+fn caught_values_stay_in_their_catch() {
+    let value = eval("(let e 1) (try (throw :x \"m\") (catch :x e 0)) (identity e)");
+    assert_eq!(value, Ok(Member::NumI(1)));
+    let value = eval("(try (throw :x \"m\") (catch :x e (error-message e)))");
+    assert_eq!(value, Ok(Member::String("m".into())));
+}
+
+#[test]
+fn calls_evaluate_a_head_that_isnt_a_name() {
+    assert_eq!(eval("((lambda (x) (+ x 1)) 1)"), Ok(Member::NumI(2)));
+    assert_eq!(eval("((if false + -) 5 3)"), Ok(Member::NumI(2)));
+    let error = eval("(1 2)").unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::Type);
+    let caught = eval("(try (1 2) (catch :type e (error-message e)))");
+    assert_eq!(caught, Ok(Member::String("1 is not callable".into())));
+}
+
+#[test]
+fn restart_parameters_stay_in_the_restart() {
+    let value = eval(
+        "(let v 7)
+         (restart-case (invoke-restart :use 3) (:use (v) v))
+         (identity v)",
+    );
+    assert_eq!(value, Ok(Member::NumI(7)));
+}
+
+#[test]
+fn the_debugger_sees_every_unhandled_error() {
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let record = seen.clone();
+    let context = ProgContext::default().with_debugger(Box::new(move |condition, restarts| {
+        record.borrow_mut().push((
+            condition.kind.to_string(),
+            restarts.iter().map(|r| r.to_string()).collect::<Vec<_>>(),
+        ));
+        restarts.iter().position(|r| *r == "use")
+    }));
+    let mut interpreter = Interpreter::with_context(context);
+    let value = interpreter.eval_str("(restart-case (+ 1 (error :low \"low\")) (:use () 5))");
+    assert_eq!(value, Ok(Member::NumI(5)));
+    let error = interpreter.eval_str("(error :bad \"bad\")").unwrap_err();
+    assert_eq!(error.to_string(), "Uncaught :bad bad");
+    let caught = interpreter.eval_str("(try (error :bad \"bad\") (catch :bad e 0))");
+    assert_eq!(caught, Ok(Member::NumI(0)));
+    drop(interpreter);
+    assert_eq!(
+        *seen.borrow(),
+        [
+            ("low".to_string(), vec!["use".to_string()]),
+            ("bad".to_string(), vec![]),
+        ]
+    );
+}
+
+#[test]
+fn vector_set_leaves_the_vector_unchanged() {
+    let value = eval("(let v [1 2]) (let w (vector-set v 0 10)) (list v w)");
+    assert_eq!(value, eval("(list [1 2] [10 2])"));
+}
+
+#[test]
+fn missing_struct_fields_are_type_errors() {
+    for (source, message) in [
+        (
+            "(defstruct point a) (point-x (make-point 1))",
+            "point has no field :x",
+        ),
+        (
+            "(%struct-get :point (make-point 1 2) :z)",
+            "point has no field :z",
+        ),
+        (
+            "(%struct-get :point (make-point 1 2) 1)",
+            "Fields are named by a keyword, got 1",
+        ),
+    ] {
+        let mut risp = Interpreter::new();
+        risp.eval_str("(defstruct point x y)").unwrap();
+        let error = risp.eval_str(source).unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::Type, "{source}");
+        assert_eq!(error.message(), message, "{source}");
+    }
+}
+
+#[test]
+fn case_type_fields_stay_in_their_clause() {
+    let value = eval(
+        "(deftype shape (circle radius) (square side))
+         (let r 7)
+         (case-type (circle 1) (circle (r) r) (_ 0))
+         (identity r)",
+    );
+    assert_eq!(value, Ok(Member::NumI(7)));
+    let error = eval("(%sum? 1 2)").unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::Type);
+}
+
+#[test]
+fn warnings_go_to_the_error_output() {
+    let mut errors = Vec::new();
+    let mut output = Vec::new();
+    let context = ProgContext::default()
+        .with_output(&mut output)
+        .with_error_output(&mut errors);
+    let value = Interpreter::with_context(context).eval_str("(warn :low \"running low\") (+ 1 0)");
+    assert_eq!(value, Ok(Member::NumI(1)));
+    drop(value);
+    assert_eq!(String::from_utf8(errors).unwrap(), "Warning: running low\n");
+    assert!(output.is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn dangling_symlinks_dont_lead_out_of_the_root() {
+    use std::{fs, os::unix::fs::symlink};
+
+    let dir = std::env::temp_dir().join(format!("risp-symlink-{}", std::process::id()));
+    let root = dir.join("box");
+    let outside = dir.join("outside_target");
+    fs::create_dir_all(&root).unwrap();
+    symlink(&outside, root.join("dl")).unwrap();
+
+    let context = ProgContext::default().with_filesystem(FsAccess::within(&root).unwrap());
+    let error = Interpreter::with_context(context)
+        .eval_str("(write-file \"dl\" \"escaped\")")
+        .unwrap_err();
+    let escaped = outside.exists();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(error.kind(), &ErrorKind::Denied);
+    assert!(!escaped);
+}
+
+#[test]
+fn multiplication() {
+    assert_eq!(eval("(* 2 3 -4)"), Ok(Member::NumI(-24)));
+    assert_eq!(eval("(*)"), Ok(Member::NumI(1)));
+    let error = eval("(* 65536 65536)").unwrap_err();
+    assert_eq!(error.message(), "Integer overflow in *");
+    let error = eval("(* 2 :a)").unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::Type);
+}
+
+#[test]
+fn closures_see_where_they_were_made() {
+    let value = eval(
+        "(let f 10)
+         (def apply-to-1 (f) (funcall f 1))
+         (apply-to-1 (lambda (x) (+ x f)))",
+    );
+    assert_eq!(value, Ok(Member::NumI(11)));
+    let value = eval(
+        "(def in-clause (v) (match v ((a b) (lambda (c) (list a b c v)))))
+         (funcall (in-clause (list 1 2)) 3)",
+    );
+    assert_eq!(value, eval("(list 1 2 3 (list 1 2))"));
+    let value =
+        eval("(def ask (base) (handle (+ (perform :ask) 1) (:ask () k (k base)))) (ask 41)");
+    assert_eq!(value, Ok(Member::NumI(42)));
+    let value = eval(
+        "(def outer (n) (last (list (def inner (m) (if (= m 0) n (inner (- m 1)))) (inner 5))))
+         (outer 7)",
+    );
+    assert_eq!(value, Ok(Member::NumI(7)));
+    let error = eval("(def caller (x) (callee)) (def callee () x) (caller 1)").unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::Unbound);
+}
+
+#[test]
+fn each_closure_keeps_its_own_captured_values() {
+    let value = eval(
+        "(def adder (n) (lambda (x) (+ x n)))
+         (let add2 (adder 2))
+         (let add5 (adder 5))
+         (def curry (a) (lambda (b) (lambda (c) (list a b c))))
+         (list (funcall add2 1) (funcall add5 1) (funcall (funcall (curry 1) 2) 3))",
+    );
+    assert_eq!(value, eval("(list 3 6 (list 1 2 3))"));
+    // binding a captured name in a call shadows it for that call only
+    let value = eval(
+        "(def tens (n) (lambda (x) (do (let n (* n 10)) (+ n x))))
+         (let f (tens 1))
+         (list (funcall f 1) (funcall f 2))",
+    );
+    assert_eq!(value, eval("(list 11 12)"));
+}
+
+#[test]
+fn deep_calls_dont_look_names_up_through_every_caller() {
+    // each lookup used to walk the scopes of every call on the stack, taking minutes here
+    let value = eval(
+        "(def f (n) (if (= n 0) 0 (+ 1 (f (- n 1)))))
+         (f 50000)",
+    );
+    assert_eq!(value, Ok(Member::NumI(50000)));
+}
+
+#[test]
+fn prelude_parameters_dont_hide_the_callers_variables() {
+    let value = eval(
+        "(let f 10) (let xs 1) (let pred 2)
+         (to-list (filter (lambda (x) (> x pred)) (map (lambda (x) (+ x f xs)) (list 1 2))))",
+    );
+    assert_eq!(value, eval("(list 12 13)"));
+}
+
+#[test]
+fn runaway_recursion_reaches_the_default_limit() {
+    let error = eval("(def f (n) (+ 1 (f n))) (f 1)").unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::StackOverflow);
+    assert!(error.message().contains(&DEFAULT_MAX_DEPTH.to_string()));
+}
+
+#[test]
+fn sources_only_live_for_their_evaluation() {
+    let mut risp = Interpreter::new();
+    for n in 0..3 {
+        let source = format!("(def add-{n} (x) (+ x {n})) (let last-{n} (add-{n} 10))");
+        risp.eval_str(&source).unwrap();
+    }
+    assert_eq!(risp.get::<i32>("last-2"), Ok(12));
+    assert_eq!(
+        risp.eval_str(&String::from("(add-1 1)")),
+        Ok(Member::NumI(2))
+    );
+}
+
+#[test]
+fn written_values_read_back() {
+    let mut risp = Interpreter::new();
+    risp.eval_str("(defstruct point x y)").unwrap();
+    for source in [
+        "(list 1 \"a\\\"b\\nc\" #\\space :k)",
+        "(vector 1 [2 3] (hash-map \"k\" [#\\a #\\newline]) (list))",
+        "(hash-set 1 (list 2 3))",
+        "(make-point 1 [2 3])",
+        "(to-list (range 3))",
+    ] {
+        let value = risp.eval_str(source).unwrap();
+        let written = value.to_string();
+        let read = risp.eval_str(&format!("(identity {written})"));
+        assert_eq!(read, Ok(value), "{written}");
+    }
+}
+
+#[test]
+fn pretty_breaks_lines_too_wide() {
+    let value = eval("(pretty [1 [2 3] {:a \"long string\"} (list :x :y)] 16)");
+    let lines = "[1\n [2 3]\n {:a \"long string\"}\n (list :x :y)]";
+    assert_eq!(value, Ok(Member::String(lines.into())));
+    let value = eval("(pretty [1 [2 3] {:a \"long string\"} (list :x :y)])");
+    let line = "[1 [2 3] {:a \"long string\"} (list :x :y)]";
+    assert_eq!(value, Ok(Member::String(line.into())));
+}
+
+#[test]
+fn display_shows_text_and_write_escapes_it() {
+    let value = eval("(format \"{}|{:s}\" \"a\\\"b\\nc\" \"a\\\"b\\nc\")");
+    assert_eq!(value, Ok(Member::String("a\"b\nc|\"a\\\"b\\nc\"".into())));
+    let value = eval("(format \"{}|{:s}\" #\\space #\\space)");
+    assert_eq!(value, Ok(Member::String(" |#\\space".into())));
+}
+
+#[test]
+fn deeply_nested_values_print() {
+    let value = eval(
+        "(def nest (v n) (if (= n 0) v (nest [v] (- n 1))))
+         (let d (nest 1 20000))
+         (list (string-length (format \"{}\" d)) (string-length (pretty d 40)))",
+    );
+    assert_eq!(value, eval("(list 40001 40001)"));
+}
+
+#[test]
+fn data_nested_past_the_depth_limit_is_walked() {
+    let context = ProgContext::default().with_prelude().with_max_depth(1000);
+    let value = Interpreter::with_context(context).eval_str(
+        "(defstruct cell value)
+         (def nest (v n) (if (= n 0) v (nest (make-cell [{:k n} v]) (- n 1))))
+         (let d (nest 1 5000))
+         (let e (nest 1 5000))
+         (list (= d e) (contains? (hash-set d) e) (string-length (format \"{}\" d)))",
+    );
+    assert_eq!(value, eval("(list true true 128894)"));
+}
+
+#[test]
+fn source_nested_too_deeply_is_a_parse_error() {
+    let nested = |depth: usize| format!("{}0{}", "(+ 1 ".repeat(depth), ")".repeat(depth));
+    assert_eq!(eval(&nested(1000)), Ok(Member::NumI(1000)));
+    let error = eval(&nested(1001)).unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::Parse);
+}
+
+#[test]
+fn names_are_only_allocated_once() {
+    // interned names are never freed, a name used by every program may not add up
+    let keyword = |source: String| match Interpreter::new().eval_str(&source) {
+        Ok(Member::Keyword(k)) => k.as_ptr(),
+        value => panic!("{value:?}"),
+    };
+    let first = keyword(String::from("(identity :interned-name)"));
+    let second = keyword(String::from("(identity :interned-name)"));
+    assert_eq!(first, second);
+}
+
+#[test]
+fn def_with_more_than_one_body_is_an_error() {
+    let error = eval("(def f (x) x x)").unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::Arity);
+}
+
+#[test]
+fn names_the_host_binds_unresolved_are_type_errors() {
+    for source in [
+        "(+ name 1)",
+        "(+ 1 name)",
+        "(+ \"a\" name)",
+        "(string name)",
+    ] {
+        let mut risp = Interpreter::new();
+        risp.set("name", Member::Ident("name"));
+        let error = risp.eval_str(source).unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::Type, "{source}");
+    }
+}
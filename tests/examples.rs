@@ -0,0 +1,69 @@
+use language::{FsAccess, Interpreter, ProgContext};
+
+/// What an example writes to the output and to the error output
+fn run(source: &str) -> (String, String) {
+    run_with(source, |context| context)
+}
+
+/// `run` in a context `grant` adds capabilities to
+fn run_with(source: &str, grant: impl FnOnce(ProgContext) -> ProgContext) -> (String, String) {
+    let mut output = Vec::new();
+    let mut errors = Vec::new();
+    let context = ProgContext::default()
+        .with_output(&mut output)
+        .with_error_output(&mut errors);
+    Interpreter::with_context(grant(context))
+        .eval_str(source)
+        .unwrap();
+    (
+        String::from_utf8(output).unwrap(),
+        String::from_utf8(errors).unwrap(),
+    )
+}
+
+#[test]
+fn continuations() {
+    let (output, errors) = run(include_str!("../examples/continuations.risp"));
+    assert_eq!(
+        output,
+        "first big: 3\nescaped: 6\nre-entered: 1\nescaped cleanly: :left\n"
+    );
+    assert_eq!(
+        errors,
+        "Warning: in\nWarning: out\nWarning: in\nWarning: out\nWarning: cleaned up\n"
+    );
+}
+
+#[test]
+fn effects() {
+    let (output, errors) = run(include_str!("../examples/effects.risp"));
+    assert_eq!(
+        output,
+        "asked twice: 40\nworked: 42\naborted: 5\naborted after cleanup: 6\nresumed into: (0 0 1 1)\n"
+    );
+    assert_eq!(errors, "Warning: starting\nWarning: cleaned up\n");
+}
+
+#[test]
+fn files() {
+    let root = std::env::temp_dir().join(format!("risp-example-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    let access = FsAccess::within(&root).unwrap();
+    let (output, errors) = run_with(include_str!("../examples/files.risp"), |context| {
+        context.with_filesystem(access)
+    });
+    let root = root.canonicalize().unwrap();
+    let left = std::fs::read_dir(&root).unwrap().count();
+    std::fs::remove_dir_all(&root).unwrap();
+    let root = root.display();
+    assert_eq!(
+        output,
+        format!(
+            "reports/2024/summary.txt\na/c\ntotal: 42\nmore\ntrue (report.txt)\nfalse\n\
+             ../../etc/passwd is outside of {root}\n/etc/passwd is outside of {root}\n"
+        )
+    );
+    assert_eq!(errors, "");
+    // the example leaves its `out` directory behind and nothing else
+    assert_eq!(left, 1);
+}
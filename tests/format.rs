@@ -0,0 +1,71 @@
+use language::{Interpreter, Member};
+
+fn eval(source: &str) -> Result<Member<'static>, language::Error> {
+    Interpreter::new().eval_str(source)
+}
+
+/// Text `format` makes of the arguments, written as in the source
+fn format(args: &str) -> String {
+    match eval(&format!("(format {args})")) {
+        Ok(Member::String(s)) => s,
+        other => panic!("{args}: {other:?}"),
+    }
+}
+
+#[test]
+fn positional_and_named_arguments_fill_the_placeholders() {
+    assert_eq!(format(r#""{} + {} = {}" 1 2 3"#), "1 + 2 = 3");
+    assert_eq!(format(r#""{1} before {0}" "a" "b""#), "b before a");
+    assert_eq!(
+        format(r#""Hello {name}, you are {age:>3}" :name "Ada" :age 36"#),
+        "Hello Ada, you are  36"
+    );
+    assert_eq!(format(r#""{{braces}} stay""#), "{braces} stay");
+}
+
+#[test]
+fn specs_align_pad_and_pick_the_radix() {
+    assert_eq!(
+        format(r#""[{:>6}] [{:<6}] [{:^7}] [{:*^9}]" "ab" "cd" "ef" "mid""#),
+        "[    ab] [cd    ] [  ef   ] [***mid***]"
+    );
+    assert_eq!(
+        format(r#""{:05} {:x} {:X} {:o} {:b} {:#>8x}" -42 255 255 8 5 48879"#),
+        "-0042 ff FF 10 101 ####beef"
+    );
+    assert_eq!(format(r#""{:.3}" "truncated""#), "tru");
+}
+
+#[test]
+fn values_are_displayed_unless_written_with_s() {
+    assert_eq!(
+        format(r#""{:a} vs {:s}" "text" "text""#),
+        r#"text vs "text""#
+    );
+    assert_eq!(format(r#""{:s} {:s}" #\a #\space"#), r"#\a #\space");
+}
+
+#[test]
+fn interpolated_strings_see_the_names_in_scope() {
+    let value = eval(r##"(def greet (name) #"Hi {name:s}, {{braces}} stay") (greet "Bob")"##);
+    assert_eq!(
+        value,
+        Ok(Member::String(r#"Hi "Bob", {braces} stay"#.into()))
+    );
+}
+
+#[test]
+fn broken_format_strings_are_errors() {
+    for (args, message) in [
+        (
+            r#""{} {}" 1"#,
+            "format has no value at position 1, it got 1",
+        ),
+        (r#""{2}" 1"#, "format has no value at position 2, it got 1"),
+        (r#""{" 1"#, "Unterminated directive in format string"),
+        (r#""{:q}" 1"#, "Invalid format spec :q"),
+    ] {
+        let error = eval(&format!("(format {args})")).unwrap_err();
+        assert!(error.to_string().contains(message), "{args}: {error}");
+    }
+}
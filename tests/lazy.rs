@@ -0,0 +1,62 @@
+use language::{Interpreter, Member, ProgContext};
+
+fn eval(source: &str) -> Result<Member<'static>, language::Error> {
+    Interpreter::new().eval_str(source)
+}
+
+#[test]
+fn unbounded_sequences_are_only_realized_as_far_as_they_are_used() {
+    let value = eval(
+        "(def nums-from (n) (lazy-seq (cons n (nums-from (+ n 1)))))
+         (list (to-list (take 3 (nums-from 7)))
+               (to-list (take 5 (iterate (lambda (x) (* x 2)) 1)))
+               (to-list (take-while (lambda (x) (< x 5)) (range)))
+               (first (drop 100000 (range))))",
+    );
+    assert_eq!(
+        value,
+        eval("(list (list 7 8 9) (list 1 2 4 8 16) (list 0 1 2 3 4) 100000)")
+    );
+}
+
+#[test]
+fn generators_run_up_to_the_yield_asked_for() {
+    let mut errors = Vec::new();
+    let context = ProgContext::default().with_error_output(&mut errors);
+    let value = Interpreter::with_context(context).eval_str(
+        "(let g (generator (do (warn \"one\") (yield 1) (warn \"two\") (yield 2))))
+         (first g)",
+    );
+    assert_eq!(value.unwrap().to_string(), "1");
+    assert_eq!(String::from_utf8(errors).unwrap(), "Warning: one\n");
+}
+
+#[test]
+fn realized_sequences_give_the_same_values_every_time() {
+    let value = eval(
+        "(let g (generator (do (yield :a) (yield :b))))
+         (list (to-list g) (to-list g))",
+    );
+    assert_eq!(value, eval("(list (list :a :b) (list :a :b))"));
+}
+
+#[test]
+fn list_builtins_take_sequences() {
+    let value = eval(
+        "(list (length (take 4 (range)))
+               (to-list (map (lambda (x) (* x x)) (take 4 (range))))
+               (empty? (drop 3 (list 1 2 3))))",
+    );
+    assert_eq!(value, eval("(list 4 (list 0 1 4 9) true)"));
+}
+
+#[test]
+fn yield_outside_of_a_generator_is_an_error() {
+    let error = eval("(yield 1)").unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("yield can only be used in a generator"),
+        "{error}"
+    );
+}
@@ -0,0 +1,53 @@
+use language::{ErrorKind, Interpreter, Member};
+
+fn eval(source: &str) -> Result<Member<'static>, language::Error> {
+    Interpreter::new().eval_str(source)
+}
+
+#[test]
+fn lists_and_vectors_destructure_into_nested_patterns() {
+    let value = eval(
+        "(let (a (b c) & more) (list 1 (list 2 3) 4 5))
+         (let [x y] [10 20])
+         (list a b c more x y)",
+    );
+    assert_eq!(value, eval("(list 1 2 3 (list 4 5) 10 20)"));
+    let value = eval("(let (_ m :as whole) (list 1 2)) (list m whole)");
+    assert_eq!(value, eval("(list 2 (list 1 2))"));
+}
+
+#[test]
+fn parameters_take_optional_and_keyword_arguments_with_defaults() {
+    let value = eval(
+        "(def scale (a &key (by 2) offset) (list (* a by) offset))
+         (list (scale 3) (scale 3 :offset 1 :by 5))",
+    );
+    assert_eq!(value, eval("(list (list 6 ()) (list 15 1))"));
+    // defaults are evaluated after the parameters before them are bound
+    let value = eval(
+        "(def sum ((a b) &optional (c (+ a b))) (list a b c))
+         (list (sum (list 1 2)) (sum (list 1 2) 9))",
+    );
+    assert_eq!(value, eval("(list (list 1 2 3) (list 1 2 9))"));
+}
+
+#[test]
+fn values_not_shaped_like_the_pattern_are_rejected() {
+    for (source, problem) in [
+        ("(let (p q) (list 1))", "missing 1 value(s) for (p q)"),
+        ("(let (p) (list 1 2))", "too many values for (p)"),
+        ("(let {:a x} 5)", "expected a map for {:a x}, got 5"),
+        ("(let {:a x} {:b 1})", "missing key :a for {:a x}"),
+        ("(let (1 x) (list 2 3))", "expected 1, got 2"),
+    ] {
+        let error = eval(source).unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::Type, "{source}");
+        assert!(error.to_string().contains(problem), "{source}: {error}");
+    }
+    let error = eval("(def f (a &key k) a) (f 1 :nope 2)").unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::Arity);
+    assert!(
+        error.to_string().contains("unknown keyword :nope"),
+        "{error}"
+    );
+}
@@ -0,0 +1,53 @@
+use language::{ErrorKind, Interpreter, Member, ProgContext};
+
+fn eval(source: &str) -> Result<Member<'static>, language::Error> {
+    Interpreter::new().eval_str(source)
+}
+
+#[test]
+fn sequence_functions_work_on_unbounded_sequences() {
+    let value = eval(
+        "(def small? (n) (< n 3))
+         (list (to-list (map inc (list 1 2 3)))
+               (to-list (take 3 (filter small? (range))))
+               (to-list (take 2 (remove small? (range))))
+               (reduce + 0 (list 1 2 3 4)))",
+    );
+    assert_eq!(
+        value,
+        eval("(list (list 2 3 4) (list 0 1 2) (list 3 4) 10)")
+    );
+}
+
+#[test]
+fn functions_combine_functions() {
+    let value = eval(
+        "(def adder (n) (lambda (x) (+ x n)))
+         (list (funcall (compose inc (adder 10)) 1) (funcall (partial + 5) 1)
+               (funcall (constantly :k) 1 2) (funcall (complement identity) false))",
+    );
+    assert_eq!(value, eval("(list 12 6 :k true)"));
+}
+
+#[test]
+fn list_helpers_walk_whole_lists() {
+    let value = eval(
+        "(list (reverse (list 1 2 3)) (append (list 1 2) (list 3)) (last (list 1 2 3))
+               (nth (list :a :b :c) 1) (any? (lambda (x) (< 3 x)) (list 1 4))
+               (every? (lambda (x) (< 3 x)) (list 1 4)) (>= 2 2) (<= 3 2))",
+    );
+    assert_eq!(
+        value,
+        eval("(list (list 3 2 1) (list 1 2 3) 3 :b true false true false)")
+    );
+}
+
+#[test]
+fn programs_can_run_without_the_prelude() {
+    let error = Interpreter::with_context(ProgContext::default())
+        .eval_str("(inc 1)")
+        .unwrap_err();
+    assert_eq!(error.kind(), &ErrorKind::Unbound);
+    let value = Interpreter::with_context(ProgContext::default()).eval_str("(+ 1 1)");
+    assert_eq!(value, Ok(Member::NumI(2)));
+}
@@ -0,0 +1,65 @@
+use language::{ErrorKind, Interpreter, Member};
+
+fn eval(source: &str) -> Result<Member<'static>, language::Error> {
+    Interpreter::new().eval_str(source)
+}
+
+fn string(s: &str) -> Result<Member<'static>, language::Error> {
+    Ok(Member::String(s.into()))
+}
+
+#[test]
+fn lengths_and_indices_count_chars() {
+    let value = eval(
+        "(list (string-length \"héllo\") (string-length \"héllo\" :bytes)
+               (substring \"Wörld\" 1 3) (string-ref \"héllo\" 1)
+               (index-of \"Wörld\" \"rl\") (index-of \"banana\" \"an\" 2) (index-of \"banana\" \"x\"))",
+    );
+    assert_eq!(value, eval("(list 5 6 \"ör\" #\\é 2 3 ())"));
+}
+
+#[test]
+fn graphemes_keep_what_reads_as_one_character_together() {
+    // a flag, a thumb with a skin tone and an e with a combining accent
+    let text = "\"🇫🇷👍🏽e\u{301}\"";
+    let value = eval(&format!(
+        "(list (string-length {text} :graphemes) (string-length {text}) (length (graphemes {text})))"
+    ));
+    assert_eq!(value, eval("(list 3 6 3)"));
+}
+
+#[test]
+fn case_maps_chars_to_strings_when_they_grow() {
+    assert_eq!(eval("(upcase \"straße\")"), string("STRASSE"));
+    assert_eq!(eval("(upcase #\\ß)"), string("SS"));
+    assert_eq!(eval("(downcase #\\Ä)"), Ok(Member::Char('ä')));
+}
+
+#[test]
+fn strings_are_split_joined_and_converted() {
+    let value = eval(
+        "(list (split \"a,b,,c\" \",\") (split \"  one two   three \")
+               (join (list \"a\" 1 #\\c) \", \") (trim \"  x  \") (replace \"banana\" \"an\" \"AN\")
+               (string->number \"ff\" 16) (string->number \"nope\") (number->string -255 16)
+               (string #\\a \"bc\" 1) (list->string (string->list \"ab\")))",
+    );
+    assert_eq!(
+        value,
+        eval(
+            "(list (list \"a\" \"b\" \"\" \"c\") (list \"one\" \"two\" \"three\")
+                   \"a, 1, c\" \"x\" \"bANANa\" 255 () \"-ff\" \"abc1\" \"ab\")"
+        )
+    );
+}
+
+#[test]
+fn indices_past_the_end_are_type_errors() {
+    for source in [
+        "(substring \"abc\" 2 9)",
+        "(string-ref \"ab\" 2)",
+        "(integer->char -1)",
+    ] {
+        let error = eval(source).unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::Type, "{source}: {error}");
+    }
+}